@@ -14,7 +14,7 @@ use bytes::Buf;
 use rseip_core::{
     cip::CommonPacketIter,
     codec::{Decode, Decoder},
-    Either, Error,
+    Either,
 };
 
 #[inline]
@@ -26,16 +26,14 @@ where
     let reply_service = decoder.decode_u8(); // buf[0]
     decoder.buf_mut().advance(1); // buf[1]
     let general_status = decoder.decode_u8(); //buf[2]
-    let extended_status_size = decoder.decode_u8(); // buf[3]
-    decoder.ensure_size((extended_status_size * 2) as usize)?;
-    let extended_status = match extended_status_size {
-        0 => None,
-        1 => Some(decoder.decode_u16()),
-        v => return Err(Error::invalid_value("one of 0,1", v)),
-    };
+    let additional_status_size = decoder.decode_u8(); // buf[3]
+    decoder.ensure_size(additional_status_size as usize * 2)?;
+    let additional = (0..additional_status_size)
+        .map(|_| decoder.decode_u16())
+        .collect();
     let status = Status {
         general: general_status,
-        extended: extended_status,
+        additional,
     };
 
     Ok((reply_service, status))
@@ -120,7 +118,7 @@ impl<'de> Decode<'de> for ForwardOpenReply {
     {
         let (reply_service, status) = decode_service_and_status(&mut decoder)?;
         let data = if status.is_err() {
-            let v = decode_forward_fail(decoder, status)?;
+            let v = decode_forward_fail(decoder, &status)?;
             Either::Right(v)
         } else {
             let v = decode_forward_open_success(decoder)?;
@@ -138,7 +136,7 @@ impl<'de> Decode<'de> for ForwardCloseReply {
     {
         let (reply_service, status) = decode_service_and_status(&mut decoder)?;
         let data = if status.is_err() {
-            let v = decode_forward_fail(decoder, status)?;
+            let v = decode_forward_fail(decoder, &status)?;
             Either::Right(v)
         } else {
             let v = decode_forward_close_success(decoder)?;
@@ -197,14 +195,17 @@ where
 #[inline]
 fn decode_forward_fail<'de, D>(
     mut decoder: D,
-    status: Status,
+    status: &Status,
 ) -> Result<ForwardRequestFail, D::Error>
 where
     D: Decoder<'de>,
 {
     let is_routing_error = status.is_routing_error();
     let max_size = if is_routing_error { 9 } else { 8 };
-    decoder.ensure_size(max_size)?;
+    if decoder.remaining() < max_size {
+        // no failure data, eg. service not supported
+        return Err(cip_error_status(status.clone()));
+    }
     let res = ForwardRequestFail {
         connection_serial_number: decoder.decode_u16(),
         originator_vendor_id: decoder.decode_u16(),
@@ -245,3 +246,27 @@ where
     }
     Err(cip_error("common packet - expect null address"))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::Bytes;
+    use rseip_core::{codec::LittleEndianDecoder, tests::CodecError};
+
+    #[test]
+    fn test_decode_additional_status() {
+        let buf = Bytes::from_static(&[
+            0xCC, 0, 0x01, 3, 0x00, 0x01, 0x02, 0x00, 0x34, 0x12,
+        ]);
+        let (reply_service, status) =
+            decode_service_and_status(LittleEndianDecoder::<CodecError>::new(buf)).unwrap();
+        assert_eq!(reply_service, 0xCC);
+        assert_eq!(status.general, 0x01);
+        assert_eq!(status.additional, [0x0100, 0x0002, 0x1234]);
+        assert_eq!(status.extended(), Some(0x0100));
+
+        // not enough words
+        let buf = Bytes::from_static(&[0xCC, 0, 0x01, 2, 0x00, 0x01]);
+        assert!(decode_service_and_status(LittleEndianDecoder::<CodecError>::new(buf)).is_err());
+    }
+}
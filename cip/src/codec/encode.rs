@@ -41,7 +41,7 @@ impl<P: Encode> OpenOptions<P> {
         dst.put_u8(transport_class_trigger);
 
         let path_len = self.connection_path.bytes_count();
-        assert!(path_len % 2 == 0 && path_len <= u8::MAX as usize);
+        assert!(path_len.is_multiple_of(2) && path_len <= u8::MAX as usize);
         dst.put_u8((path_len / 2) as u8);
 
         Ok(())
@@ -62,7 +62,7 @@ impl<P: Encode> OpenOptions<P> {
             v |= (parameters.redundant_owner as u32) << 31;
             dst.put_u32_le(v);
         } else {
-            let mut v = parameters.connection_size & 0x01FF;
+            let mut v = parameters.connection_size.min(0x01FF);
             v |= (parameters.variable_length as u16) << 9;
             v |= (parameters.priority as u16) << 10;
             v |= (parameters.connection_type as u16) << 13;
@@ -113,7 +113,7 @@ impl<P: Encode> ForwardCloseRequest<P> {
         dst.put_u32_le(self.originator_serial_number);
 
         let path_len = self.connection_path.bytes_count();
-        assert!(path_len % 2 == 0 && path_len <= u8::MAX as usize);
+        assert!(path_len.is_multiple_of(2) && path_len <= u8::MAX as usize);
         dst.put_u8(path_len as u8 / 2); //path size
         dst.put_u8(0); // reserved
 
@@ -205,3 +205,33 @@ where
         4 + data_len + data_len % 2 + 2 + self.path.bytes_count()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::service::{SERVICE_FORWARD_OPEN, SERVICE_LARGE_FORWARD_OPEN};
+    use byteorder::{ByteOrder, LittleEndian};
+    use rseip_core::tests::EncodeExt;
+
+    #[test]
+    fn test_encode_large_forward_open() {
+        let opts = OpenOptions::default().connection_size(4000);
+        assert!(!opts.large_open);
+        let opts = opts.large_open(true);
+        assert_eq!(opts.service_code(), SERVICE_LARGE_FORWARD_OPEN);
+        let path_len = opts.connection_path.bytes_count();
+        assert_eq!(opts.bytes_count(), 40 + path_len);
+        let buf = opts.clone().try_into_bytes().unwrap();
+        assert_eq!(buf.len(), 40 + path_len);
+        // o_t network connection parameters, 32 bits
+        assert_eq!(LittleEndian::read_u32(&buf[26..30]) & 0xFFFF, 4000);
+
+        let opts = opts.without_large_open();
+        assert!(!opts.large_open);
+        assert_eq!(opts.service_code(), SERVICE_FORWARD_OPEN);
+        let buf = opts.try_into_bytes().unwrap();
+        assert_eq!(buf.len(), 36 + path_len);
+        // o_t network connection parameters, 16 bits
+        assert_eq!(LittleEndian::read_u16(&buf[26..28]) & 0x01FF, 511);
+    }
+}
@@ -35,7 +35,7 @@ impl Encode for PortSegment {
 
         buf.put_slice(&self.link);
         let end_pos = buf.len();
-        if (end_pos - start_pos) % 2 != 0 {
+        if !(end_pos - start_pos).is_multiple_of(2) {
             buf.put_u8(0);
         }
         Ok(())
@@ -144,7 +144,7 @@ impl Segment {
         buf.put_u8(0x91);
         buf.put_u8(char_count as u8);
         buf.put_slice(symbol);
-        if char_count % 2 != 0 {
+        if !char_count.is_multiple_of(2) {
             buf.put_u8(0);
         }
         Ok(())
@@ -197,7 +197,7 @@ impl Encode for Segment {
             },
             Segment::Port(port) => port.bytes_count(),
             Segment::Symbol(symbol) => {
-                let char_count = symbol.as_bytes().len();
+                let char_count = symbol.len();
                 2 + char_count + char_count % 2
             }
         }
@@ -288,6 +288,6 @@ mod test {
 
         let buf = epath.try_into_bytes().unwrap();
 
-        assert_eq!(&buf[..], &[01, 00]);
+        assert_eq!(&buf[..], &[0x01, 0x00]);
     }
 }
@@ -6,16 +6,25 @@
 
 use crate::{
     epath::{EPath, PortSegment, Segment},
+    service::{SERVICE_FORWARD_OPEN, SERVICE_LARGE_FORWARD_OPEN},
     MessageReply, MessageReplyInterface, Status,
 };
 use bytes::Bytes;
+use core::fmt;
 use rand::Rng;
 use rseip_core::Either;
 
+/// max connection size of Forward Open
+pub const MAX_CONNECTION_SIZE: u16 = 511;
+
+/// max connection size of Large Forward Open
+pub const MAX_LARGE_CONNECTION_SIZE: u16 = 4000;
+
 /// connection type enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ConnectionType {
     /// may be used to reconfigure the connection
+    #[default]
     Null = 0,
     /// supported for CIP transport class 0 and class 1
     Multicast = 1,
@@ -23,65 +32,45 @@ pub enum ConnectionType {
     P2P = 2,
 }
 
-impl Default for ConnectionType {
-    fn default() -> Self {
-        Self::Null
-    }
-}
-
 /// connection priority enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Priority {
     /// low priority
     Low = 0,
     /// high priority
     High = 1,
     /// scheduled priority
+    #[default]
     Scheduled = 2,
     /// urgent priority
     Urgent = 3,
 }
 
-impl Default for Priority {
-    fn default() -> Self {
-        Self::Scheduled
-    }
-}
-
 /// transport direction
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Direction {
     /// act as client
+    #[default]
     Client = 0,
     /// act as server
     Server = 1,
 }
 
-impl Default for Direction {
-    fn default() -> Self {
-        Self::Client
-    }
-}
-
 /// transport trigger type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TriggerType {
+    #[default]
     Cyclic = 0,
     ChangeOfState = 1,
     /// application object
     Application = 2,
 }
 
-impl Default for TriggerType {
-    fn default() -> Self {
-        Self::Cyclic
-    }
-}
-
 /// A 16-bit sequence count value is prepended to all Class 1, 2, and 3 transports
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TransportClass {
     /// either a client OR a server
+    #[default]
     Class0 = 0,
     /// either a client OR a server
     Class1 = 1,
@@ -97,31 +86,21 @@ pub enum TransportClass {
     Class6 = 6,
 }
 
-impl Default for TransportClass {
-    fn default() -> Self {
-        Self::Class0
-    }
-}
-
 /// fixed length or variable length
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum VariableLength {
     /// fixed length
+    #[default]
     Fixed = 0,
     /// variable length
     Variable = 1,
 }
 
-impl Default for VariableLength {
-    fn default() -> Self {
-        Self::Fixed
-    }
-}
-
 /// realtime format
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ReadlTimeFormat {
     /// connection is pure data and is modeless
+    #[default]
     Modeless = 0,
     /// use zero data length packet to indicate idle mode
     ZeroLength = 1,
@@ -131,12 +110,6 @@ pub enum ReadlTimeFormat {
     Header32Bit = 4,
 }
 
-impl Default for ReadlTimeFormat {
-    fn default() -> Self {
-        Self::Modeless
-    }
-}
-
 /// forward open connection parameters
 #[derive(Debug, Clone)]
 pub struct ConnectionParameters {
@@ -223,6 +196,74 @@ pub struct ForwardRequestFail {
     pub remaining_path_size: Option<u8>,
 }
 
+/// Forward Open rejected by the target
+#[derive(Debug, Clone)]
+pub struct ForwardOpenError {
+    /// general status and extended status
+    pub status: Status,
+    /// from request
+    pub connection_serial_number: u16,
+    /// from request
+    pub originator_vendor_id: u16,
+    /// from request
+    pub originator_serial_number: u32,
+    /// size of words ,only present with routing type errors
+    pub remaining_path_size: Option<u8>,
+}
+
+impl ForwardOpenError {
+    #[inline]
+    pub fn new(status: Status, fail: ForwardRequestFail) -> Self {
+        Self {
+            status,
+            connection_serial_number: fail.connection_serial_number,
+            originator_vendor_id: fail.originator_vendor_id,
+            originator_serial_number: fail.originator_serial_number,
+            remaining_path_size: fail.remaining_path_size,
+        }
+    }
+
+    /// connection in use or duplicate forward open;
+    /// a new connection serial number is required
+    #[inline]
+    pub fn is_duplicate(&self) -> bool {
+        self.status.general == 0x01 && self.status.extended() == Some(0x0100)
+    }
+
+    /// connection manager explanation of the status
+    #[cfg(feature = "error-explain")]
+    #[inline]
+    pub fn reason(&self) -> Option<&'static str> {
+        match (self.status.general, self.status.extended()) {
+            (0x01, Some(v)) => crate::status::connection_failure_reason(v),
+            _ => None,
+        }
+    }
+
+    /// connection manager explanation of the status
+    #[cfg(not(feature = "error-explain"))]
+    #[inline]
+    pub fn reason(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+impl fmt::Display for ForwardOpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "forward open failed: connection serial number {:#06x}, {}",
+            self.connection_serial_number, self.status
+        )?;
+        if let Some(v) = self.remaining_path_size {
+            write!(f, "\n\tremaining path size: {} words", v)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ForwardOpenError {}
+
 /// forward close request
 #[derive(Debug, Default)]
 pub struct ForwardCloseRequest<P> {
@@ -382,7 +423,9 @@ impl<P> OpenOptions<P> {
         self
     }
 
-    /// connection size
+    /// connection size, up to [`MAX_CONNECTION_SIZE`] bytes of Forward Open, or
+    /// [`MAX_LARGE_CONNECTION_SIZE`] bytes of Large Forward Open, see [`OpenOptions::large_open`];
+    /// not checked here, larger sizes are rejected by the target
     pub fn connection_size(mut self, val: u16) -> Self {
         self.o_t_params.connection_size = val;
         self.t_o_params.connection_size = val;
@@ -461,12 +504,44 @@ impl<P> OpenOptions<P> {
         self
     }
 
-    /// is large forward open
+    /// is large forward open, required for connection size greater than [`MAX_CONNECTION_SIZE`];
+    /// falls back to Forward Open if the target does not support it
     pub fn large_open(mut self, val: bool) -> Self {
         self.large_open = val;
         self
     }
 
+    /// service code of Forward Open or Large Forward Open
+    #[inline]
+    pub fn service_code(&self) -> u8 {
+        if self.large_open {
+            SERVICE_LARGE_FORWARD_OPEN
+        } else {
+            SERVICE_FORWARD_OPEN
+        }
+    }
+
+    /// random connection serial number, eg. after duplicate forward open
+    #[inline]
+    pub fn renew_connection_serial_number(mut self) -> Self {
+        let mut rng = rand::thread_rng();
+        loop {
+            let v = rng.gen_range(1..0xFFFF);
+            if v != self.connection_serial_number {
+                self.connection_serial_number = v;
+                return self;
+            }
+        }
+    }
+
+    /// options of Forward Open, connection size limited to 511 bytes;
+    /// for targets not supporting Large Forward Open
+    #[inline]
+    pub fn without_large_open(self) -> Self {
+        let size = self.o_t_params.connection_size.min(MAX_CONNECTION_SIZE);
+        self.large_open(false).connection_size(size)
+    }
+
     /// get transport class trigger
     pub(crate) fn transport_class_trigger(&self) -> u8 {
         let dir = self.transport_direction as u8;
@@ -507,3 +582,31 @@ impl Default for OpenOptions<EPath> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward_open_error() {
+        let fail = ForwardRequestFail {
+            connection_serial_number: 0x1234,
+            ..Default::default()
+        };
+        let status = Status::new(0x01).with_additional(0x0100);
+        let err = ForwardOpenError::new(status, fail);
+        assert!(err.is_duplicate());
+        #[cfg(feature = "error-explain")]
+        assert_eq!(
+            err.reason(),
+            Some("Connection in use or duplicate forward open")
+        );
+        assert!(err.to_string().contains("0x1234"));
+
+        let opts = OpenOptions::default();
+        let serial_number = opts.connection_serial_number;
+        let opts = opts.renew_connection_serial_number();
+        assert_ne!(opts.connection_serial_number, serial_number);
+        assert_ne!(opts.connection_serial_number, 0);
+    }
+}
@@ -13,7 +13,7 @@ use smallvec::{smallvec, SmallVec};
 pub const EPATH_CONNECTION_MANAGER: &[u8] = &[0x20, 0x06, 0x24, 0x01];
 
 /// Segment of EPATH
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Segment {
     /// symbolic, ANSI Ext. String
     Symbol(String),
@@ -43,7 +43,7 @@ impl Segment {
 type Array = [Segment; 4];
 
 /// EPATH
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct EPath(SmallVec<Array>);
 
 impl EPath {
@@ -170,7 +170,7 @@ impl From<Vec<Segment>> for EPath {
 }
 
 /// EPATH Port Segment
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct PortSegment {
     /// Port to leave Current Node (1 if Backplane)
     pub port: u16,
@@ -9,7 +9,9 @@ use crate::service::*;
 use crate::*;
 use crate::{epath::EPath, error::cip_error};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use rseip_core::codec::{BytesHolder, Decode, Decoder, Encode, Encoder, LittleEndianDecoder};
+use rseip_core::codec::{
+    visitor, BytesHolder, Decode, Decoder, Encode, Encoder, LittleEndianDecoder,
+};
 use smallvec::SmallVec;
 
 /// build and send multiple service packet
@@ -101,7 +103,25 @@ where
     /// decode next message reply from the multiple service reply
     pub fn next<Item>(&mut self) -> Option<Result<MessageReply<Item>, D::Error>>
     where
-        Item: Decode<'de>,
+        Item: Decode<'de> + 'de,
+    {
+        self.next_reply()
+    }
+
+    /// decode next message reply from the multiple service reply;
+    /// unlike [`ReplyIter::next`], error status of the reply will not be raised as error,
+    /// check it with [`MessageReply::status`]
+    pub fn next_unchecked<Item>(&mut self) -> Option<Result<MessageReply<Item>, D::Error>>
+    where
+        Item: Decode<'de> + 'de,
+    {
+        let res = self.next_reply::<IgnoreStatusInterceptor<Item>>()?;
+        Some(res.map(|v| v.0))
+    }
+
+    fn next_reply<R>(&mut self) -> Option<Result<R, D::Error>>
+    where
+        R: Decode<'de> + 'de,
     {
         let buf = self.buf.as_mut()?;
         let count = if let Some(count) = self.count {
@@ -137,13 +157,13 @@ where
                 if buf.remaining() < size {
                     return self.raise_err();
                 }
-                let res: Result<MessageReply<Item>, _> = buf.decode_any();
+                let res: Result<R, _> = buf.decode_sized(size, visitor::any());
                 return Some(res);
             }
         }
         // process remaining
         if buf.remaining() > 0 {
-            let res: Result<MessageReply<Item>, _> = buf.decode_any();
+            let res: Result<R, _> = buf.decode_any();
             self.buf.take();
             return Some(res);
         }
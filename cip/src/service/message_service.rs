@@ -20,6 +20,26 @@ pub trait MessageService: Send + Sync {
         D: Encode + Send + Sync,
         R: MessageReplyInterface + Decode<'de> + 'static;
 
+    /// send message requests, replies are in the same order as requests;
+    /// requests may be pipelined by the transport, i.e. sent before waiting for replies
+    #[allow(clippy::type_complexity)]
+    async fn send_all<'de, P, D, R>(
+        &mut self,
+        requests: Vec<MessageRequest<P, D>>,
+    ) -> Result<Vec<Result<R, Self::Error>>, Self::Error>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+        Self::Error: Send,
+    {
+        let mut res = Vec::with_capacity(requests.len());
+        for mr in requests {
+            res.push(self.send(mr).await);
+        }
+        Ok(res)
+    }
+
     /// close underline transport
     async fn close(&mut self) -> Result<(), Self::Error>;
 
@@ -40,6 +60,20 @@ impl<T: MessageService + Sized> MessageService for &mut T {
         (**self).send(mr).await
     }
 
+    #[inline]
+    async fn send_all<'de, P, D, R>(
+        &mut self,
+        requests: Vec<MessageRequest<P, D>>,
+    ) -> Result<Vec<Result<R, Self::Error>>, Self::Error>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+        Self::Error: Send,
+    {
+        (**self).send_all(requests).await
+    }
+
     #[inline]
     async fn close(&mut self) -> Result<(), Self::Error> {
         (**self).close().await
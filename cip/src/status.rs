@@ -7,13 +7,35 @@
 use core::fmt;
 
 /// message reply status
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Status {
     pub general: u8,
-    pub extended: Option<u16>,
+    /// raw additional status words, the first one is the extended status
+    pub additional: Vec<u16>,
 }
 
 impl Status {
+    #[inline]
+    pub fn new(general: u8) -> Self {
+        Self {
+            general,
+            additional: Default::default(),
+        }
+    }
+
+    /// append additional status word
+    #[inline]
+    pub fn with_additional(mut self, word: u16) -> Self {
+        self.additional.push(word);
+        self
+    }
+
+    /// the first additional status word
+    #[inline]
+    pub fn extended(&self) -> Option<u16> {
+        self.additional.first().copied()
+    }
+
     #[inline]
     pub fn is_ok(&self) -> bool {
         self.general == 0
@@ -27,7 +49,7 @@ impl Status {
     #[inline]
     pub fn is_routing_error(&self) -> bool {
         // EIP-CIP-V1-3.3  3.5.5.4
-        match (self.general, self.extended) {
+        match (self.general, self.extended()) {
             (1, Some(0x0204)) => true,
             (1, Some(0x0311)) => true,
             (1, Some(0x0312)) => true,
@@ -38,6 +60,17 @@ impl Status {
         }
     }
 
+    /// CIP connection lost or not found, the connection should be opened again
+    #[inline]
+    pub fn is_connection_error(&self) -> bool {
+        match (self.general, self.extended()) {
+            (1, Some(0x0107)) => true,
+            (1, Some(0x0203)) => true,
+            (7, _) => true,
+            _ => false,
+        }
+    }
+
     #[inline]
     pub fn into_result(self) -> Result<(), Status> {
         if self.general == 0 {
@@ -46,63 +79,211 @@ impl Status {
             Err(self)
         }
     }
+
+    /// explanation of the status
+    #[cfg(feature = "error-explain")]
+    #[inline]
+    pub fn explain(&self) -> &'static str {
+        let extended = self.extended();
+        match self.general {
+            0x01 => extended.and_then(connection_failure_reason),
+            _ => match extended {
+                Some(v) => logix_extended_reason(self.general, v),
+                None => logix_general_reason(self.general),
+            },
+        }
+        .unwrap_or_else(|| general_status_text(self.general))
+    }
 }
 
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "CIP general status: {}", self.general)?;
-        if let Some(v) = self.extended {
+        if let Some((v, rest)) = self.additional.split_first() {
             write!(f, ", extended status: {}", v)?;
+            if !rest.is_empty() {
+                write!(f, ", additional status: {:?}", rest)?;
+            }
         }
         #[cfg(feature = "error-explain")]
-        {
-            let msg = match self.general {
-                0x00 => return Ok(()),
-                0x01 => match self.extended {
-                    Some(0x0103) => "Transport class and trigger combination not supported",
-                    Some(0x0204) => "timeout",
-                    Some(0x0205) => "Invalid SocketAddr Info item",
-                    Some(0x0302) => "Network bandwidth not available for data",
-                    Some(0x0311) => "Invalid Port ID specified in the Route_Path field",
-                    Some(0x0312) => "Invalid Node Address specified in the Route_Path field",
-                    Some(0x0315) =>"Invalid segment type in the Route_Path field",
-                    _ => "Connection failure",
-                },
-                0x02 => "Resource error", // processing unconnected send request
-                0x03 => "Bad parameter",
-                0x04 => "Request path segment error",
-                0x05 => "Request path destination unknown", //Probably instance number is not present
-                0x06 => "Partial transfer",
-                0x07 => "Connection lost",
-                0x08 => "Service not supported",
-                0x09 => "Invalid attribute value",
-                0x0A => "Attribute list error",
-                0x0B => "Already in requested mode/state",
-                0x0C => "Object state conflict",
-                0x0D => "Object already exists",
-                0x0E => "Attribute not settable",
-                0x0F => "Privilege violation",
-                0x10 => match self.extended {
-                    Some(0x2101) => "Device state conflict: keyswitch position: The requestor is changing force information in HARD RUN mode",
-                    Some(0x2802) => "Device state conflict: Safety Status: Unable to modify Safety Memory in the current controller state",
-                    _ => "Device state conflict",
-                },
-                0x11 => "Reply data too large",
-                0x13 => "Insufficient Request Data: Data too short for expected parameters",
-                0x014 => "Attribute not supported",
-                0x26 => "The Request Path Size received was shorter or longer than expected",
-                0xFF => match self.extended {
-                        Some(0x2104) =>  "Offset is beyond end of the requested tag",
-                        Some(0x2105) =>  "Number of Elements extends beyond the end of the requested tag",
-                        Some(0x2107) =>  "Tag type used in request does not match the data type of the target tag",
-                        _ =>  "General Error: Unknown",
-                },
-                _ => "General Error: Unknown",
-            };
-            write!(f, "\n\t{}", msg)?;
+        if self.general != 0 {
+            write!(f, "\n\t{}", self.explain())?;
         }
         Ok(())
     }
 }
 
 impl std::error::Error for Status {}
+
+/// CIP general status codes, EIP-CIP-V1 Appendix B
+#[cfg(feature = "error-explain")]
+pub(crate) fn general_status_text(general: u8) -> &'static str {
+    match general {
+        0x00 => "Success",
+        0x01 => "Connection failure",
+        0x02 => "Resource unavailable",
+        0x03 => "Invalid parameter value",
+        0x04 => "Path segment error",
+        0x05 => "Path destination unknown",
+        0x06 => "Partial transfer",
+        0x07 => "Connection lost",
+        0x08 => "Service not supported",
+        0x09 => "Invalid attribute value",
+        0x0A => "Attribute list error",
+        0x0B => "Already in requested mode/state",
+        0x0C => "Object state conflict",
+        0x0D => "Object already exists",
+        0x0E => "Attribute not settable",
+        0x0F => "Privilege violation",
+        0x10 => "Device state conflict",
+        0x11 => "Reply data too large",
+        0x12 => "Fragmentation of a primitive value",
+        0x13 => "Not enough data",
+        0x14 => "Attribute not supported",
+        0x15 => "Too much data",
+        0x16 => "Object does not exist",
+        0x17 => "Service fragmentation sequence not in progress",
+        0x18 => "No stored attribute data",
+        0x19 => "Store operation failure",
+        0x1A => "Routing failure, request packet too large",
+        0x1B => "Routing failure, response packet too large",
+        0x1C => "Missing attribute list entry data",
+        0x1D => "Invalid attribute value list",
+        0x1E => "Embedded service error",
+        0x1F => "Vendor specific error",
+        0x20 => "Invalid parameter",
+        0x21 => "Write-once value or medium already written",
+        0x22 => "Invalid reply received",
+        0x23 => "Buffer overflow",
+        0x24 => "Message format error",
+        0x25 => "Key failure in path",
+        0x26 => "Path size invalid",
+        0x27 => "Unexpected attribute in list",
+        0x28 => "Invalid member ID",
+        0x29 => "Member not settable",
+        0x2A => "Group 2 only server general failure",
+        0x2B => "Unknown Modbus error",
+        0x2C => "Attribute not gettable",
+        0x2D => "Instance not deletable",
+        0x2E => "Service not supported for specified path",
+        0x2F..=0xCF => "Reserved",
+        _ => "Object class specific error",
+    }
+}
+
+/// Logix texts of general status codes replied without extended status, 1756-PM020
+#[cfg(feature = "error-explain")]
+pub(crate) fn logix_general_reason(general: u8) -> Option<&'static str> {
+    let msg = match general {
+        0x04 => "Request path segment error: syntax error or tag not found",
+        0x05 => "Request path destination unknown: probably instance number is not present",
+        0x06 => {
+            "Insufficient packet space: not enough room in the response buffer for all the data"
+        }
+        0x0A => "Attribute list error: generally attribute not supported",
+        0x13 => "Insufficient request data: data too short for expected parameters",
+        0x1C => "Attribute list shortage: too few attribute numbers for the number of attributes",
+        0x26 => "Request path size received was shorter or longer than expected",
+        _ => return None,
+    };
+    Some(msg)
+}
+
+/// Logix extended status codes, 1756-PM020;
+/// the table is partial, codes not listed are explained by the general status
+#[cfg(feature = "error-explain")]
+pub(crate) fn logix_extended_reason(general: u8, extended: u16) -> Option<&'static str> {
+    let msg = match (general, extended) {
+        (0x10, 0x2101) => "Device state conflict: keyswitch position: The requestor is changing force information in HARD RUN mode",
+        (0x10, 0x2802) => "Device state conflict: Safety Status: Unable to modify Safety Memory in the current controller state",
+        (0xFF, 0x2104) => "Offset is beyond end of the requested tag",
+        (0xFF, 0x2105) => "Number of Elements extends beyond the end of the requested tag",
+        (0xFF, 0x2107) => "Tag type used in request does not match the data type of the target tag",
+        _ => return None,
+    };
+    Some(msg)
+}
+
+/// reason of connection failure by the extended status, for general status 0x01
+#[cfg(feature = "error-explain")]
+pub(crate) fn connection_failure_reason(extended: u16) -> Option<&'static str> {
+    let msg = match extended {
+        0x0100 => "Connection in use or duplicate forward open",
+        0x0103 => "Transport class and trigger combination not supported",
+        0x0106 => "Ownership conflict",
+        0x0107 => "Target connection not found",
+        0x0108 => "Invalid network connection parameter",
+        0x0109 => "Invalid connection size",
+        0x0110 => "Target for connection not configured",
+        0x0111 => "RPI not supported",
+        0x0113 => "Out of connections",
+        0x0114 => "Vendor ID or product code mismatch",
+        0x0115 => "Device type mismatch",
+        0x0116 => "Revision mismatch",
+        0x0117 => "Invalid produced or consumed application path",
+        0x0118 => "Invalid or inconsistent configuration application path",
+        0x0119 => "Non-listen only connection not opened",
+        0x011A => "Target object out of connections",
+        0x011B => "RPI is smaller than the production inhibit time",
+        0x0203 => "Connection timed out",
+        0x0204 => "Unconnected request timed out",
+        0x0205 => "Parameter error in unconnected request",
+        0x0206 => "Message too large for Unconnected Send service",
+        0x0207 => "Unconnected acknowledge without reply",
+        0x0301 => "No buffer memory available",
+        0x0302 => "Network bandwidth not available for data",
+        0x0303 => "No consumed connection ID filter available",
+        0x0304 => "Not configured to send scheduled priority data",
+        0x0305 => "Schedule signature mismatch",
+        0x0306 => "Schedule signature validation not possible",
+        0x0311 => "Invalid Port ID specified in the Route_Path field",
+        0x0312 => "Invalid Node Address specified in the Route_Path field",
+        0x0315 => "Invalid segment type in the Route_Path field",
+        0x0316 => "Error in Forward Close service connection path",
+        0x0317 => "Scheduling not specified",
+        0x0318 => "Link address to self invalid",
+        0x0319 => "Secondary resources unavailable",
+        0x031A => "Rack connection already established",
+        0x031B => "Module connection already established",
+        0x031C => "Miscellaneous",
+        0x031D => "Redundant connection mismatch",
+        0x031E => {
+            "No more user configurable link consumer resources available in the producing module"
+        }
+        0x031F => "No user configurable link consumer resources configured in the producing module",
+        0x0800 => "Network link offline",
+        0x0810 => "No target application data available",
+        0x0811 => "No originator application data available",
+        0x0812 => "Node address has changed since the network was scheduled",
+        0x0813 => "Not configured for off-subnet multicast",
+        _ => return None,
+    };
+    Some(msg)
+}
+
+#[cfg(all(test, feature = "error-explain"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain() {
+        let status = Status::new(0x04);
+        assert_eq!(
+            status.explain(),
+            "Request path segment error: syntax error or tag not found"
+        );
+        let status = Status::new(0xFF).with_additional(0x2107);
+        assert_eq!(
+            status.explain(),
+            "Tag type used in request does not match the data type of the target tag"
+        );
+        // extended status not listed
+        let status = Status::new(0x04).with_additional(0x1234);
+        assert_eq!(status.explain(), "Path segment error");
+        let status = Status::new(0x01).with_additional(0x0100);
+        assert_eq!(
+            status.explain(),
+            "Connection in use or duplicate forward open"
+        );
+    }
+}
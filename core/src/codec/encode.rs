@@ -5,10 +5,12 @@
 // License: MIT
 
 mod impls;
+mod little_endian;
 mod slice;
 
 use crate::Error;
 use bytes::BytesMut;
+pub use little_endian::LittleEndianEncoder;
 pub use slice::SliceContainer;
 
 pub trait Encoder {
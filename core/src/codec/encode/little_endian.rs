@@ -0,0 +1,121 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::*;
+use bytes::{BufMut, Bytes};
+use core::marker::PhantomData;
+
+/// little endian encoder
+#[derive(Debug, PartialEq)]
+pub struct LittleEndianEncoder<E> {
+    _marker: PhantomData<E>,
+}
+
+impl<E> LittleEndianEncoder<E> {
+    pub fn new() -> Self {
+        Self {
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<E> Default for LittleEndianEncoder<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Error> LittleEndianEncoder<E> {
+    /// encode `item` to bytes
+    #[inline]
+    pub fn encode_to_bytes<T: Encode>(&mut self, item: T) -> Result<Bytes, E> {
+        let mut buf = BytesMut::with_capacity(item.bytes_count());
+        item.encode(&mut buf, self)?;
+        Ok(buf.freeze())
+    }
+}
+
+impl<E: Error> Encoder for LittleEndianEncoder<E> {
+    type Error = E;
+
+    #[inline(always)]
+    fn encode_bool(&mut self, item: bool, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_u8(if item { 255 } else { 0 });
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_i8(&mut self, item: i8, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_i8(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_u8(&mut self, item: u8, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_u8(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_i16(&mut self, item: i16, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_i16_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_u16(&mut self, item: u16, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_u16_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_i32(&mut self, item: i32, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_i32_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_u32(&mut self, item: u32, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_u32_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_i64(&mut self, item: i64, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_i64_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_u64(&mut self, item: u64, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_u64_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_f32(&mut self, item: f32, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_f32_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_f64(&mut self, item: f64, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_f64_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_i128(&mut self, item: i128, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_i128_le(item);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn encode_u128(&mut self, item: u128, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        buf.put_u128_le(item);
+        Ok(())
+    }
+}
@@ -14,6 +14,24 @@ pub trait Error: Sized + StdError + From<io::Error> {
     /// Raised when there is general error when decoding a type.
     fn custom<T: fmt::Display>(msg: T) -> Self;
 
+    /// Raised when an operation does not complete in time.
+    fn timeout<T: fmt::Display>(msg: T) -> Self {
+        Self::from(io::Error::new(io::ErrorKind::TimedOut, msg.to_string()))
+    }
+
+    /// Raised when the message reply carries a CIP error status;
+    /// `additional` is the additional status words, the first one is the extended status.
+    fn cip_status<T: fmt::Display>(general: u8, additional: &[u16], msg: T) -> Self {
+        let _ = (general, additional);
+        Self::custom(msg)
+    }
+
+    /// Raised when the encapsulation reply carries an error status.
+    fn encapsulation_status<T: fmt::Display>(code: u16, msg: T) -> Self {
+        let _ = code;
+        Self::custom(msg)
+    }
+
     /// Raised when receives a type different from what it was expecting.
     fn invalid_type<U: fmt::Display, E: fmt::Display>(unexp: U, exp: E) -> Self {
         Self::custom(format_args!("invalid type: {}, expected {}", unexp, exp))
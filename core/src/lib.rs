@@ -16,7 +16,6 @@ MIT
 //#![warn(missing_docs)]
 #![allow(clippy::match_like_matches_macro)]
 
-#[cfg_attr(feature = "no_std", macro_use)]
 extern crate alloc;
 
 pub extern crate smallvec;
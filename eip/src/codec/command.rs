@@ -13,7 +13,7 @@ impl<C: Command, E: Error> Encoder<C> for ClientCodec<E> {
     type Error = E;
     #[inline(always)]
     fn encode(&mut self, item: C, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        item.encode(dst, self)
+        item.encode(dst, &mut self.encoder)
     }
 }
 
@@ -168,6 +168,7 @@ impl<D: Encode> Encode for SendRRData<D> {
         };
         pkt.hdr.command = Self::command_code();
         pkt.hdr.session_handle = self.session_handle;
+        pkt.hdr.sender_context = self.sender_context;
         pkt.encode(buf, encoder)
     }
 
@@ -186,6 +187,7 @@ impl<D: Encode> Encode for SendRRData<D> {
         };
         pkt.hdr.command = Self::command_code();
         pkt.hdr.session_handle = self.session_handle;
+        pkt.hdr.sender_context = self.sender_context;
         pkt.encode(buf, encoder)
     }
 
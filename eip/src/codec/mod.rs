@@ -12,109 +12,27 @@ use crate::{
     EncapsulationHeader, EncapsulationPacket,
 };
 use byteorder::{ByteOrder, LittleEndian};
-use bytes::{BufMut, Bytes, BytesMut};
-use core::marker::PhantomData;
+use bytes::{Bytes, BytesMut};
 use rseip_core::{
-    codec::{self, Decode, Encode, LittleEndianDecoder},
+    codec::{self, Decode, Encode, LittleEndianDecoder, LittleEndianEncoder},
     Error,
 };
 use tokio_util::codec::{Decoder, Encoder};
 
+/// frames encapsulation packets, encoding is delegated to [`LittleEndianEncoder`]
 #[derive(Debug, PartialEq)]
 pub struct ClientCodec<E> {
-    _marker: PhantomData<E>,
+    encoder: LittleEndianEncoder<E>,
 }
 
 impl<E> ClientCodec<E> {
     pub(crate) fn new() -> Self {
         Self {
-            _marker: Default::default(),
+            encoder: Default::default(),
         }
     }
 }
 
-impl<E: Error> codec::Encoder for ClientCodec<E> {
-    type Error = E;
-
-    #[inline(always)]
-    fn encode_bool(&mut self, item: bool, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u8(if item { 255 } else { 0 });
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_i8(&mut self, item: i8, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_i8(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_u8(&mut self, item: u8, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u8(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_i16(&mut self, item: i16, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_i16_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_u16(&mut self, item: u16, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u16_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_i32(&mut self, item: i32, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_i32_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_u32(&mut self, item: u32, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u32_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_i64(&mut self, item: i64, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_i64_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_u64(&mut self, item: u64, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u64_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_f32(&mut self, item: f32, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_f32_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_f64(&mut self, item: f64, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_f64_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_i128(&mut self, item: i128, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_i128_le(item);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn encode_u128(&mut self, item: u128, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u128_le(item);
-        Ok(())
-    }
-}
-
 impl<I, E> Encoder<EncapsulationPacket<I>> for ClientCodec<E>
 where
     I: codec::Encode + Sized,
@@ -127,7 +45,7 @@ where
         item: EncapsulationPacket<I>,
         buf: &mut BytesMut,
     ) -> Result<(), Self::Error> {
-        item.encode(buf, self)
+        item.encode(buf, &mut self.encoder)
     }
 }
 
@@ -147,16 +65,10 @@ impl<E: Error> Decoder for ClientCodec<E> {
                 "below u16::MAX",
             ));
         }
+        // the remaining buffer is the next packet, eg. replies of pipelined requests
         if src.len() < ENCAPSULATION_HEADER_LEN + data_len {
             return Ok(None);
         }
-        if src.len() > ENCAPSULATION_HEADER_LEN + data_len {
-            // should no remaining buffer
-            return Err(E::invalid_length(
-                src.len(),
-                ENCAPSULATION_HEADER_LEN + data_len,
-            ));
-        }
         let header_bytes = src.split_to(ENCAPSULATION_HEADER_LEN).freeze();
         let decoder = LittleEndianDecoder::<E>::new(header_bytes);
         let hdr = EncapsulationHeader::decode(decoder)?;
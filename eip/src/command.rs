@@ -90,6 +90,8 @@ pub struct SendRRData<D> {
     /// - set to 0, rely on the timeout mechanism of the encapsulated protocol
     /// - usually set to 0 for CIP
     pub timeout: u16,
+    /// echoed by the target, used to match the reply
+    pub sender_context: [u8; 8],
     /// Data to be Sent via Unconnected Message
     pub data: D,
 }
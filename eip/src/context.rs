@@ -9,25 +9,44 @@ use super::{
     framed::Framed,
     EncapsulationPacket,
 };
-use crate::{codec::ClientCodec, *};
+use crate::{
+    codec::ClientCodec,
+    consts::{EIP_COMMAND_SEND_RRDATA, EIP_COMMAND_SEND_UNIT_DATA},
+    *,
+};
 use byteorder::{ByteOrder, LittleEndian};
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::Bytes;
 use core::fmt;
 use futures_util::{SinkExt, StreamExt};
 use rseip_core::{
     cip::CommonPacketIter,
     codec::{Encode, LittleEndianDecoder},
 };
-use tokio::io::{AsyncRead, AsyncWrite};
+use std::{collections::HashMap, io, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    time,
+};
 
 pub type CommonPacket<'a, E> = CommonPacketIter<'a, LittleEndianDecoder<E>>;
 
+/// key to match the reply of a request, see [`EipContext::post_rrdata`] and [`EipContext::post_unit_data`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReplyKey {
+    /// sender context of SendRRData
+    SenderContext([u8; 8]),
+    /// sequence number of SendUnitData
+    Sequence(u16),
+}
+
 /// EIP context
 pub struct EipContext<T, E: Error> {
     framed: Framed<T, ClientCodec<E>>,
     session_handle: u32,
-    #[allow(unused)]
-    sender_context: Bytes,
+    /// sender context of the next SendRRData, increased for each request
+    sender_context: u64,
+    /// max time to wait for a reply
+    timeout: Option<Duration>,
 }
 
 impl<T, E: Error> fmt::Debug for EipContext<T, E> {
@@ -35,22 +54,35 @@ impl<T, E: Error> fmt::Debug for EipContext<T, E> {
         f.debug_struct("EipContext")
             .field("session_handle", &self.session_handle)
             .field("sender_context", &self.sender_context)
+            .field("timeout", &self.timeout)
             .field("framed", &"<Framed>")
             .finish()
     }
 }
 
 impl<T, E: Error> EipContext<T, E> {
-    /// set sender context
+    /// set sender context of the next SendRRData, it is increased for each request
     #[allow(unused)]
     #[inline]
     pub fn with_sender_context(&mut self, sender_context: [u8; 8]) -> &mut Self {
-        let mut buf = BytesMut::new();
-        buf.put_slice(&sender_context);
-        self.sender_context = buf.freeze();
+        self.sender_context = u64::from_le_bytes(sender_context);
         self
     }
 
+    /// max time to wait for a reply, none to wait forever, default none
+    #[inline]
+    pub fn with_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[inline]
+    fn next_sender_context(&mut self) -> [u8; 8] {
+        let res = self.sender_context.to_le_bytes();
+        self.sender_context = self.sender_context.wrapping_add(1);
+        res
+    }
+
     /// current session handle
     #[inline]
     pub fn session_handle(&self) -> Option<u32> {
@@ -81,7 +113,24 @@ where
         Self {
             framed,
             session_handle: 0,
-            sender_context: Bytes::from_static(&[0, 0, 0, 0, 0, 0, 0, 0]),
+            sender_context: 0,
+            timeout: None,
+        }
+    }
+
+    /// wait for the next packet
+    #[inline]
+    async fn next_packet(&mut self) -> Result<EncapsulationPacket<Bytes>, E> {
+        let item = match self.timeout {
+            Some(dur) => match time::timeout(dur, self.framed.next()).await {
+                Ok(item) => item,
+                Err(_) => return Err(E::timeout(format_args!("no reply received in {:?}", dur))),
+            },
+            None => self.framed.next().await,
+        };
+        match item {
+            Some(item) => item,
+            None => Err(transport_closed()),
         }
     }
 
@@ -94,14 +143,9 @@ where
         let code = C::command_code();
         log::trace!("send command: {:#0x?}", code);
         self.framed.send(cmd).await?;
-        match self.framed.next().await {
-            Some(item) => {
-                let pkt: EncapsulationPacket<Bytes> = item?;
-                pkt.hdr.ensure_command::<E>(code)?;
-                Ok(pkt)
-            }
-            None => Err(E::custom("transport closed")),
-        }
+        let pkt = self.next_packet().await?;
+        pkt.hdr.ensure_command::<E>(code)?;
+        Ok(pkt)
     }
 
     /// send command: NOP
@@ -115,7 +159,7 @@ where
     /// send command: ListIdentity
     #[allow(unused)]
     #[inline]
-    pub async fn list_identity<'de>(&mut self) -> Result<CommonPacket<'static, E>, E> {
+    pub async fn list_identity(&mut self) -> Result<CommonPacket<'static, E>, E> {
         let pkt = self.send_and_reply(command::ListIdentity).await?;
         let res = CommonPacketIter::new(LittleEndianDecoder::<E>::new(pkt.data))?;
         Ok(res)
@@ -124,7 +168,7 @@ where
     /// send command: ListServices
     #[allow(unused)]
     #[inline]
-    pub async fn list_service<'de>(&mut self) -> Result<CommonPacket<'static, E>, E> {
+    pub async fn list_service(&mut self) -> Result<CommonPacket<'static, E>, E> {
         let pkt = self.send_and_reply(command::ListServices).await?;
         CommonPacket::new(LittleEndianDecoder::<E>::new(pkt.data))
     }
@@ -132,7 +176,7 @@ where
     /// send command: ListInterface
     #[allow(unused)]
     #[inline]
-    pub async fn list_interface<'de>(&mut self) -> Result<CommonPacket<'static, E>, E> {
+    pub async fn list_interface(&mut self) -> Result<CommonPacket<'static, E>, E> {
         let pkt = self.send_and_reply(command::ListInterfaces).await?;
         CommonPacket::new(LittleEndianDecoder::<E>::new(pkt.data))
     }
@@ -177,45 +221,261 @@ where
 
     ///  send command: SendRRData
     #[inline]
-    pub async fn send_rrdata<'de, D>(&mut self, data: D) -> Result<CommonPacket<'static, E>, E>
+    pub async fn send_rrdata<D>(&mut self, data: D) -> Result<CommonPacket<'static, E>, E>
     where
         D: Encode,
     {
-        let pkt = self
-            .send_and_reply(command::SendRRData {
+        let key = self.post_rrdata(data).await?;
+        let mut res = self.recv_replies(&[key]).await?;
+        Ok(res.pop().expect("expected reply"))
+    }
+
+    /// send command: SendUnitData
+    #[inline]
+    pub async fn send_unit_data<D>(
+        &mut self,
+        connection_id: u32,
+        sequence_number: u16,
+        data: D,
+    ) -> Result<CommonPacket<'static, E>, E>
+    where
+        D: Encode,
+    {
+        let key = self
+            .post_unit_data(connection_id, sequence_number, data)
+            .await?;
+        let mut res = self.recv_replies(&[key]).await?;
+        Ok(res.pop().expect("expected reply"))
+    }
+
+    /// queue command SendRRData without waiting for the reply, the command is sent by
+    /// [`EipContext::recv_reply`] or [`EipContext::recv_replies`];
+    /// several requests can be in flight, replies are matched by the returned key
+    #[inline]
+    pub async fn post_rrdata<D>(&mut self, data: D) -> Result<ReplyKey, E>
+    where
+        D: Encode,
+    {
+        let sender_context = self.next_sender_context();
+        log::trace!("post command: SendRRData");
+        self.framed
+            .feed(command::SendRRData {
                 session_handle: self.session_handle,
                 timeout: 0,
+                sender_context,
                 data,
             })
             .await?;
-        let interface_handle = LittleEndian::read_u32(&pkt.data[0..4]); // interface handle
-        debug_assert_eq!(interface_handle, 0);
-        // timeout = &pkt.data[4..6]
-        CommonPacket::new(LittleEndianDecoder::<E>::new(pkt.data.slice(6..)))
+        Ok(ReplyKey::SenderContext(sender_context))
     }
 
-    /// send command: SendUnitData
+    /// queue command SendUnitData without waiting for the reply, the command is sent by
+    /// [`EipContext::recv_reply`] or [`EipContext::recv_replies`];
+    /// several requests can be in flight, replies are matched by the returned key
     #[inline]
-    pub async fn send_unit_data<'de, D>(
+    pub async fn post_unit_data<D>(
         &mut self,
         connection_id: u32,
         sequence_number: u16,
         data: D,
-    ) -> Result<CommonPacket<'static, E>, E>
+    ) -> Result<ReplyKey, E>
     where
         D: Encode,
     {
-        let pkt = self
-            .send_and_reply(command::SendUnitData {
+        log::trace!("post command: SendUnitData");
+        self.framed
+            .feed(command::SendUnitData {
                 session_handle: self.session_handle,
                 sequence_number,
                 connection_id,
                 data,
             })
             .await?;
+        Ok(ReplyKey::Sequence(sequence_number))
+    }
+
+    /// send queued commands, then wait for the next reply of SendRRData or SendUnitData
+    pub async fn recv_reply(&mut self) -> Result<(ReplyKey, CommonPacket<'static, E>), E> {
+        // flush the write buffer, whatever the item type
+        SinkExt::<command::Nop<()>>::flush(&mut self.framed).await?;
+        let pkt = self.next_packet().await?;
+        if pkt.data.len() < 6 {
+            return Err(E::invalid_length(pkt.data.len(), 6));
+        }
         let interface_handle = LittleEndian::read_u32(&pkt.data[0..4]); // interface handle
         debug_assert_eq!(interface_handle, 0);
         // timeout = &pkt.data[4..6]
-        CommonPacketIter::new(LittleEndianDecoder::<E>::new(pkt.data.slice(6..)))
+        let data = pkt.data.slice(6..);
+        let key = match pkt.hdr.command {
+            EIP_COMMAND_SEND_RRDATA => ReplyKey::SenderContext(pkt.hdr.sender_context),
+            EIP_COMMAND_SEND_UNIT_DATA => match connected_sequence(&data) {
+                Some(v) => ReplyKey::Sequence(v),
+                None => {
+                    return Err(E::invalid_value(
+                        "common packet without connected data item",
+                        "connected data item of SendUnitData",
+                    ))
+                }
+            },
+            _ => {
+                return Err(E::invalid_value(
+                    format_args!("command code {:#0x?}", pkt.hdr.command),
+                    "SendRRData or SendUnitData",
+                ))
+            }
+        };
+        let cpf = CommonPacketIter::new(LittleEndianDecoder::<E>::new(data))?;
+        Ok((key, cpf))
+    }
+
+    /// wait for replies of the keys, replies are in the same order as keys;
+    /// unexpected replies are discarded, eg. replies of abandoned requests
+    pub async fn recv_replies(
+        &mut self,
+        keys: &[ReplyKey],
+    ) -> Result<Vec<CommonPacket<'static, E>>, E> {
+        let mut pending: HashMap<ReplyKey, usize> =
+            keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+        let mut res: Vec<_> = keys.iter().map(|_| None).collect();
+        while !pending.is_empty() {
+            let (key, cpf) = self.recv_reply().await?;
+            match pending.remove(&key) {
+                Some(i) => res[i] = Some(cpf),
+                None => log::trace!("discard unexpected reply: {:?}", key),
+            }
+        }
+        Ok(res
+            .into_iter()
+            .map(|v| v.expect("expected reply"))
+            .collect())
+    }
+}
+
+/// sequence number of the connected data item, the second item of the common packet
+fn connected_sequence(buf: &[u8]) -> Option<u16> {
+    // item count | address type | address length | address | data type | data length | sequence number
+    let addr_len = LittleEndian::read_u16(buf.get(4..6)?) as usize;
+    let pos = 6 + addr_len + 4;
+    buf.get(pos..pos + 2).map(LittleEndian::read_u16)
+}
+
+/// the stream ended, reported as io error so that callers can tell transport failures
+#[inline]
+fn transport_closed<E: Error>() -> E {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "transport closed").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rseip_core::tests::CodecError;
+
+    #[test]
+    fn test_connected_sequence() {
+        let buf = [
+            0x02, 0x00, // item count
+            0xA1, 0x00, 0x04, 0x00, 0x01, 0x02, 0x03, 0x04, // connected address
+            0xB1, 0x00, 0x06, 0x00, // connected data item
+            0x34, 0x12, // sequence number
+            0xCC, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(connected_sequence(&buf), Some(0x1234));
+        assert_eq!(connected_sequence(&buf[..14]), None);
+    }
+
+    /// encapsulation packet of SendRRData or SendUnitData reply
+    fn reply_packet(command: u16, sender_context: [u8; 8], items: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&command.to_le_bytes());
+        buf.extend_from_slice(&(6 + items.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&1_u32.to_le_bytes()); // session handle
+        buf.extend_from_slice(&0_u32.to_le_bytes()); // status
+        buf.extend_from_slice(&sender_context);
+        buf.extend_from_slice(&0_u32.to_le_bytes()); // options
+        buf.extend_from_slice(&[0; 6]); // interface handle | timeout
+        buf.extend_from_slice(items);
+        buf
+    }
+
+    fn rrdata_reply(sender_context: u8, data: u8) -> Vec<u8> {
+        let items = [
+            0x02, 0x00, // item count
+            0x00, 0x00, 0x00, 0x00, // null address
+            0xB2, 0x00, 0x01, 0x00, data, // unconnected data item
+        ];
+        reply_packet(
+            EIP_COMMAND_SEND_RRDATA,
+            [sender_context, 0, 0, 0, 0, 0, 0, 0],
+            &items,
+        )
+    }
+
+    fn unit_data_reply(sequence: u8, data: u8) -> Vec<u8> {
+        let items = [
+            0x02, 0x00, // item count
+            0xA1, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00, // connected address
+            0xB1, 0x00, 0x03, 0x00, sequence, 0x00, data, // connected data item
+        ];
+        reply_packet(EIP_COMMAND_SEND_UNIT_DATA, [0; 8], &items)
+    }
+
+    /// last byte of the data item
+    fn reply_data(mut cpf: CommonPacket<'static, CodecError>) -> u8 {
+        cpf.next_item().unwrap().unwrap(); // address item
+        let item = cpf.next_item().unwrap().unwrap();
+        *item.data.last().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies() {
+        use tokio::io::AsyncWriteExt;
+
+        let (stream, mut peer) = tokio::io::duplex(1024);
+        let mut ctx = EipContext::<_, CodecError>::new(stream);
+        ctx.with_sender_context([1, 0, 0, 0, 0, 0, 0, 0]);
+        let keys = [
+            ctx.post_rrdata(()).await.unwrap(),
+            ctx.post_rrdata(()).await.unwrap(),
+            ctx.post_unit_data(1, 7, ()).await.unwrap(),
+            ctx.post_unit_data(1, 8, ()).await.unwrap(),
+        ];
+        assert_eq!(keys[0], ReplyKey::SenderContext([1, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(keys[1], ReplyKey::SenderContext([2, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(keys[3], ReplyKey::Sequence(8));
+
+        // out of order, with replies of abandoned requests
+        let replies = [
+            rrdata_reply(9, 0xF0),
+            unit_data_reply(8, 4),
+            unit_data_reply(3, 0xF1),
+            rrdata_reply(2, 2),
+            unit_data_reply(7, 3),
+            rrdata_reply(1, 1),
+            rrdata_reply(9, 0xF2),
+        ];
+        for reply in replies {
+            peer.write_all(&reply).await.unwrap();
+        }
+        let res = ctx.recv_replies(&keys).await.unwrap();
+        let res: Vec<_> = res.into_iter().map(reply_data).collect();
+        assert_eq!(res, [1, 2, 3, 4]);
+
+        // late reply left, then nothing
+        let (key, cpf) = ctx.recv_reply().await.unwrap();
+        assert_eq!(key, ReplyKey::SenderContext([9, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(reply_data(cpf), 0xF2);
+        ctx.with_timeout(Some(Duration::from_millis(10)));
+        assert!(ctx.recv_reply().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (stream, _peer) = tokio::io::duplex(1024);
+        let mut ctx = EipContext::<_, CodecError>::new(stream);
+        ctx.with_timeout(Some(Duration::from_millis(10)));
+        match ctx.register_session().await {
+            Err(CodecError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            res => panic!("expected timeout, got {:?}", res),
+        }
     }
 }
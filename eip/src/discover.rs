@@ -32,6 +32,7 @@ pub struct EipDiscovery<E> {
     broadcast_addr: SocketAddrV4,
     times: Option<usize>,
     interval: Duration,
+    timeout: Option<Duration>,
     _marker: PhantomData<E>,
 }
 
@@ -44,6 +45,7 @@ impl<E> EipDiscovery<E> {
             broadcast_addr: SocketAddrV4::new(Ipv4Addr::BROADCAST, EIP_DEFAULT_PORT),
             times: Some(1),
             interval: Duration::from_secs(1),
+            timeout: None,
             _marker: Default::default(),
         }
     }
@@ -75,6 +77,13 @@ impl<E> EipDiscovery<E> {
         self.interval = interval;
         self
     }
+
+    /// stop discovery after the timeout, even if sending forever
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 }
 
 impl<E> EipDiscovery<E>
@@ -118,7 +127,8 @@ where
             }
         };
 
-        let rx = stream::unfold((rx, Box::pin(tx_fut)), |mut state| async move {
+        let deadline = self.timeout.map(|v| time::Instant::now() + v);
+        let rx = stream::unfold((rx, Box::pin(tx_fut)), move |mut state| async move {
             loop {
                 tokio::select! {
                     res = state.0.next() => {
@@ -140,6 +150,9 @@ where
                         dbg!("cancel rx");
                         return None;
                     },
+                    _ = time::sleep_until(deadline.unwrap_or_else(time::Instant::now)), if deadline.is_some() => {
+                        return None;
+                    },
                 }
             }
         });
@@ -33,12 +33,12 @@ pub(crate) fn eip_error_code<E: Error>(err_code: u16) -> E {
             0x0064 => "An originator used an invalid session handle when sending an encapsulation message to the target",
             0x0065 => "The target received a message of invalid length",
             0x0069 => "Unsupported encapsulation protocol revision",
-            _ =>  return E::custom(build_error!(err_code)),
+            _ =>  return E::encapsulation_status(err_code, build_error!(err_code)),
     };
-    E::custom(build_error!(err_code, msg))
+    E::encapsulation_status(err_code, build_error!(err_code, msg))
 }
 
 #[cfg(not(feature = "error-explain"))]
 pub(crate) fn eip_error_code<E: Error>(err_code: u16) -> E {
-    E::custom(build_error!(err_code))
+    E::encapsulation_status(err_code, build_error!(err_code))
 }
@@ -43,16 +43,13 @@ where
 
     #[inline]
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let inner = Pin::new(&mut self.get_mut().inner);
-        match inner.poll_next(cx) {
-            Poll::Ready(Some(Ok(item))) => {
-                if item.hdr.command == EIP_COMMAND_NOP {
-                    Poll::Pending
-                } else {
-                    Poll::Ready(Some(Ok(item)))
-                }
+        let inner = &mut self.get_mut().inner;
+        loop {
+            // skip NOP, then poll again so that the waker is registered
+            match Pin::new(&mut *inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) if item.hdr.command == EIP_COMMAND_NOP => continue,
+                v => return v,
             }
-            v => v,
         }
     }
 }
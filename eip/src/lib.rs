@@ -16,7 +16,7 @@ pub mod encapsulation;
 mod error;
 mod framed;
 
-pub use context::EipContext;
+pub use context::{EipContext, ReplyKey};
 pub use discover::EipDiscovery;
 pub use encapsulation::{EncapsulationHeader, EncapsulationPacket};
 pub use rseip_core::{
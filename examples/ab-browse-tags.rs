@@ -0,0 +1,29 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+//! list user tags of controller scope and program scopes
+
+use anyhow::Result;
+use rseip::precludes::*;
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let mut client = AbEipClient::new_host_lookup("192.168.0.83")
+        .await?
+        .with_connection_path(PortSegment::default());
+    let tree = client.browse_tags().await?;
+    for item in tree.controller.iter() {
+        println!("{:?}", item);
+    }
+    for program in tree.programs.iter() {
+        println!("{}:", program.program.name);
+        for item in program.tags.iter() {
+            println!("  {:?}", item);
+        }
+    }
+    client.close().await?;
+    Ok(())
+}
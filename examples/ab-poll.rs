@@ -0,0 +1,39 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+//! poll tags by rate groups, print value changes
+
+use anyhow::Result;
+use futures::StreamExt;
+use rseip::client::ab_eip::*;
+use rseip::precludes::*;
+use std::time::Duration;
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let client = AbEipClient::new_host_lookup("192.168.0.83")
+        .await?
+        .with_connection_path(PortSegment::default());
+    let mut poller = Poller::new(client);
+    poller.add(
+        PollTag::new(EPath::parse_tag("test_car1_x")?, Duration::from_millis(100)).deadband(10.0),
+    );
+    poller.add(PollTag::new(EPath::parse_tag("test_frag")?, Duration::from_secs(1)).count(10));
+    let stream = poller.into_stream().take(20);
+    futures::pin_mut!(stream);
+    while let Some(event) = stream.next().await {
+        match event? {
+            Ok(event) => println!(
+                "{:?}: {:?} -> {:?}",
+                event.tag,
+                event.old.map(|v| v.value),
+                event.new.value
+            ),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+    Ok(())
+}
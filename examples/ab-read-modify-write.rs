@@ -37,6 +37,12 @@ pub async fn main() -> Result<()> {
     client.read_modify_write(req).await?;
     let value: (TagType, [u8; 4]) = client.read_tag(tag).await?;
     println!("value after read_modify_write: {:#02x?}", value);
+    // bit-terminated path, written with read_modify_write;
+    // mask width from the tag type of the read reply
+    let req = BitRequest::parse("test_car1_x.7")?.with_tag_type(value.0)?;
+    client.write_bit(req.clone(), true).await?;
+    let bit = client.read_bit(req).await?;
+    println!("bit 7 after write_bit: {}", bit);
     client.close().await?;
     Ok(())
 }
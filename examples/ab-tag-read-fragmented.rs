@@ -5,12 +5,12 @@
 // License: MIT
 
 #![allow(unused)]
+#![allow(clippy::field_reassign_with_default)]
 
 use anyhow::Result;
-use bytes::{BufMut, BytesMut};
+use bytes::BytesMut;
+use rseip::client::ab_eip::*;
 use rseip::precludes::*;
-use rseip::{client::ab_eip::*, ClientError};
-use rseip_core::codec::{Decoder, LittleEndianDecoder};
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
@@ -18,104 +18,33 @@ pub async fn main() -> Result<()> {
         AbEipConnection::new_host_lookup("192.168.0.83", OpenOptions::default()).await?;
     let tag = EPath::parse_tag("test_frag")?;
     println!("read tag...");
-    let mut buf = BytesMut::new();
-    let mut tag_type = None;
-    loop {
-        let req = ReadFragmentedRequest::new()
-            .tag(tag.clone())
-            .offset(buf.len() as u16);
-        let (has_more, value) = client.read_tag_fragmented(req).await.unwrap();
-        tag_type = Some(value.tag_type);
-        let bytes = value.value;
-        dbg!(bytes.len());
-        buf.put_slice(&bytes[..]);
-        if !has_more {
-            break;
-        }
-    }
-    println!("buf.len: {}", buf.len());
-    let mut decoder = LittleEndianDecoder::<ClientError>::new(buf.freeze());
-    let udt: BigUdt = decoder.decode_any()?;
-    println!("tag type: {:?}", tag_type);
-    println!("tag value: {:?}", udt);
+    let value: TagValue<BigUdt> = client.read_tag_large(tag).await?;
+    println!("tag type: {:?}", value.tag_type);
+    println!("tag value: {:?}", value.value);
 
     client.close().await?;
     Ok(())
 }
 
-const DEFAULT_STRING_CAPACITY: usize = 82;
-/// total bytes = 4 + 82
-#[derive(Debug, Default)]
-struct AbString {
-    data: String,
-}
-
-impl Encode for AbString {
-    fn encode_by_ref<A: rseip_core::codec::Encoder>(
-        &self,
-        buf: &mut BytesMut,
-        encoder: &mut A,
-    ) -> Result<(), A::Error> {
-        let mut data = self.data.as_bytes();
-        if data.len() > DEFAULT_STRING_CAPACITY {
-            data = &data[0..DEFAULT_STRING_CAPACITY];
-        }
-        // LEN
-        buf.put_u16_le(data.len() as u16);
-        buf.put_u16_le(0);
-        //DATA
-        let remaining = DEFAULT_STRING_CAPACITY - data.len();
-        buf.put_slice(data);
-        if remaining > 0 {
-            buf.put_bytes(0, remaining);
-        }
-        Ok(())
-    }
-
-    fn bytes_count(&self) -> usize {
-        4 + DEFAULT_STRING_CAPACITY
-    }
-}
-
-impl<'de> Decode<'de> for AbString {
-    fn decode<D>(mut decoder: D) -> Result<Self, D::Error>
-    where
-        D: rseip_core::codec::Decoder<'de>,
-    {
-        decoder.ensure_size(4 + DEFAULT_STRING_CAPACITY)?;
-        let len = decoder.decode_u16() as usize;
-        let _ = decoder.decode_u16();
-        let mut data = [0; DEFAULT_STRING_CAPACITY];
-        let mut i = 0;
-        while i < len {
-            data[i] = decoder.decode_u8();
-            i += 1;
-        }
-        Ok(Self {
-            data: String::from_utf8_lossy(&data[0..len]).to_string(),
-        })
-    }
-}
-
-/// total bytes = (4 + 82) * 16 = 1408
+/// total bytes = 88 * 16 = 1408
 #[derive(Debug, Default)]
 struct BigUdt {
-    member1: AbString,
-    member2: AbString,
-    member3: AbString,
-    member4: AbString,
-    member5: AbString,
-    member6: AbString,
-    member7: AbString,
-    member8: AbString,
-    member9: AbString,
-    member10: AbString,
-    member11: AbString,
-    member12: AbString,
-    member13: AbString,
-    member14: AbString,
-    member15: AbString,
-    member16: AbString,
+    member1: STRING,
+    member2: STRING,
+    member3: STRING,
+    member4: STRING,
+    member5: STRING,
+    member6: STRING,
+    member7: STRING,
+    member8: STRING,
+    member9: STRING,
+    member10: STRING,
+    member11: STRING,
+    member12: STRING,
+    member13: STRING,
+    member14: STRING,
+    member15: STRING,
+    member16: STRING,
 }
 
 impl Encode for BigUdt {
@@ -130,6 +59,7 @@ impl Encode for BigUdt {
         self.member4.encode_by_ref(buf, encoder)?;
         self.member5.encode_by_ref(buf, encoder)?;
         self.member6.encode_by_ref(buf, encoder)?;
+        self.member7.encode_by_ref(buf, encoder)?;
         self.member8.encode_by_ref(buf, encoder)?;
         self.member9.encode_by_ref(buf, encoder)?;
         self.member10.encode_by_ref(buf, encoder)?;
@@ -143,7 +73,7 @@ impl Encode for BigUdt {
     }
 
     fn bytes_count(&self) -> usize {
-        16 * 86
+        16 * STRING::SIZE
     }
 }
 
@@ -27,6 +27,7 @@ pub async fn main() -> Result<()> {
     Ok(())
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct TheIdentity<'a> {
     /// device manufacturers vendor id
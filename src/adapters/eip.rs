@@ -94,6 +94,74 @@ where
         Ok(reply)
     }
 
+    /// send all requests before waiting for replies, replies are matched by sender context
+    async fn unconnected_send_all<'de, CP, P, D, R>(
+        &mut self,
+        requests: Vec<UnconnectedSend<CP, MessageRequest<P, D>>>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        CP: Encode + Send + Sync,
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
+        let mut keys = Vec::with_capacity(requests.len());
+        let mut service_codes = Vec::with_capacity(requests.len());
+        for request in requests {
+            service_codes.push(request.data.service_code);
+            let unconnected_send: MessageRequest<&[u8], _> = MessageRequest {
+                service_code: SERVICE_UNCONNECTED_SEND,
+                path: EPATH_CONNECTION_MANAGER,
+                data: request,
+            };
+            keys.push(self.post_rrdata(unconnected_send).await?);
+        }
+        let replies = self.recv_replies(&keys).await?;
+        let res = replies
+            .into_iter()
+            .zip(service_codes)
+            .map(|(cpf, service_code)| {
+                let reply: R = message_reply::decode_unconnected_send(cpf)?;
+                reply.expect_service::<ClientError>(service_code + 0x80)?;
+                Ok(reply)
+            })
+            .collect();
+        Ok(res)
+    }
+
+    /// send all requests before waiting for replies, replies are matched by sequence number
+    async fn connected_send_all<'de, P, D, R>(
+        &mut self,
+        connection_id: u32,
+        requests: Vec<(u16, MessageRequest<P, D>)>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
+        let mut keys = Vec::with_capacity(requests.len());
+        let mut service_codes = Vec::with_capacity(requests.len());
+        for (sequence_number, request) in requests {
+            service_codes.push(request.service_code);
+            keys.push(
+                self.post_unit_data(connection_id, sequence_number, request)
+                    .await?,
+            );
+        }
+        let replies = self.recv_replies(&keys).await?;
+        let res = replies
+            .into_iter()
+            .zip(service_codes)
+            .map(|(cpf, service_code)| {
+                let (_, reply): (u16, R) = message_reply::decode_connected_send(cpf)?;
+                reply.expect_service::<ClientError>(service_code + 0x80)?;
+                Ok(reply)
+            })
+            .collect();
+        Ok(res)
+    }
+
     /// open CIP connection
     #[inline]
     async fn forward_open<P>(&mut self, request: OpenOptions<P>) -> Result<ForwardOpenReply>
@@ -101,7 +169,7 @@ where
         P: Encode + Send + Sync,
     {
         let req: MessageRequest<&[u8], _> = MessageRequest {
-            service_code: SERVICE_FORWARD_OPEN,
+            service_code: request.service_code(),
             path: EPATH_CONNECTION_MANAGER,
             data: request,
         };
@@ -58,6 +58,48 @@ pub trait Service: Send + Sync {
         D: Encode + Send + Sync,
         R: MessageReplyInterface + Decode<'de> + 'static;
 
+    /// unconnected send of several requests, replies are in the same order as requests;
+    /// transports may send all requests before waiting for replies
+    async fn unconnected_send_all<'de, CP, P, D, R>(
+        &mut self,
+        requests: Vec<UnconnectedSend<CP, MessageRequest<P, D>>>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        CP: Encode + Send + Sync,
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
+        let mut res = Vec::with_capacity(requests.len());
+        for request in requests {
+            res.push(self.unconnected_send(request).await);
+        }
+        Ok(res)
+    }
+
+    /// connected send of several requests with their sequence numbers,
+    /// replies are in the same order as requests;
+    /// transports may send all requests before waiting for replies
+    async fn connected_send_all<'de, P, D, R>(
+        &mut self,
+        connection_id: u32,
+        requests: Vec<(u16, MessageRequest<P, D>)>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
+        let mut res = Vec::with_capacity(requests.len());
+        for (sequence_number, request) in requests {
+            res.push(
+                self.connected_send(connection_id, sequence_number, request)
+                    .await,
+            );
+        }
+        Ok(res)
+    }
+
     /// forward open
     async fn forward_open<P>(&mut self, request: OpenOptions<P>) -> Result<ForwardOpenReply>
     where
@@ -4,21 +4,30 @@
 // Copyright: 2021, Joylei <leingliu@gmail.com>
 // License: MIT
 
+mod bits;
+mod browse;
+pub mod dynamic;
 pub(crate) mod interceptor;
 mod path;
+mod poll;
 mod service;
+mod string;
 mod symbol;
 pub mod template;
 pub mod value;
 
 use super::*;
+pub use bits::{BitRequest, BOOL_ARRAY_BITS};
+pub use browse::{ProgramTags, TagTree};
 use futures_util::future::BoxFuture;
-pub use path::{PathError, PathParser};
+pub use path::{PathError, PathParser, WritePath};
+pub use poll::{ChangeEvent, PollError, PollTag, Poller, Sample, TagId};
 use rseip_cip::Status;
 pub use rseip_eip::EipContext;
 pub use service::*;
 use std::net::SocketAddrV4;
-pub use symbol::{GetInstanceAttributeList, SymbolInstance};
+pub use string::{AbString, STRING, STRING_CAPACITY, STRING_STRUCTURE_HANDLE};
+pub use symbol::{ExternalAccess, GetInstanceAttributeList, SymbolAttribute, SymbolInstance};
 pub use template::AbTemplateService;
 use tokio::net::TcpStream;
 pub use value::*;
@@ -41,6 +50,9 @@ pub type AbEipClient = Client<AbEipDriver>;
 /// AB EIP Connection
 pub type AbEipConnection = Connection<AbEipDriver>;
 
+/// AB EIP client pool
+pub type AbEipPool = Pool<AbEipDriver>;
+
 /// AB EIP driver
 pub struct AbEipDriver;
 
@@ -0,0 +1,290 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::{
+    path::split_bit,
+    service::{ab_read_modify_write, ab_read_tag},
+    symbol::SymbolType,
+    PathError, PathParser, ReadModifyWriteRequest, TagType, TagValue,
+};
+use crate::{cip::epath::EPath, cip::service::MessageService, ClientError, Result};
+use bytes::{Buf, Bytes};
+use rseip_core::codec::{Encode, LittleEndianEncoder};
+use rseip_core::Error;
+
+/// number of BOOLs packed into one DWORD element of a BOOL array
+pub const BOOL_ARRAY_BITS: u32 = 32;
+
+/// a single bit of a BOOL array or an integer tag
+///
+/// ```rust,ignore
+/// // bools[37] is bit 5 of the DWORD element bools[1]
+/// let req = BitRequest::bool_array(EPath::parse_tag("bools")?, 37);
+/// client.write_bit(req.clone(), true).await?;
+/// let value = client.read_bit(req).await?;
+///
+/// // bit 3 of a DINT member
+/// let req = BitRequest::new(EPath::parse_tag("udt.flags")?, TagType::Dint, 3)?;
+/// client.write_bit(req, false).await?;
+///
+/// // bit-terminated path, host type from symbol or template info
+/// let req = BitRequest::parse("Motor.Status.3")?.with_symbol_type(member.type_info)?;
+/// client.write_bit(req, true).await?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitRequest {
+    tag: EPath,
+    tag_type: Option<TagType>,
+    bit: u8,
+}
+
+impl BitRequest {
+    /// bit of an integer tag, eg. `SINT`, `INT`, `DINT`, `LINT` or `DWORD`;
+    /// the tag type decides the mask width of writes
+    pub fn new(tag: EPath, tag_type: TagType, bit: u8) -> Result<Self> {
+        let size = bit_host_size(tag_type).ok_or_else(|| {
+            ClientError::invalid_type(format_args!("{:?}", tag_type), "integer tag type")
+        })?;
+        if bit as usize >= size * 8 {
+            return Err(Error::invalid_value(
+                format_args!("bit {}", bit),
+                format_args!("bit within {:?}", tag_type),
+            ));
+        }
+        Ok(Self {
+            tag,
+            tag_type: Some(tag_type),
+            bit,
+        })
+    }
+
+    /// bit-terminated tag path, eg. `Motor.Status.3`;
+    /// the tag type of the host value is not known, set it with [`BitRequest::with_tag_type`]
+    /// or [`BitRequest::with_symbol_type`] before writes
+    pub fn parse(path: impl AsRef<[u8]>) -> Result<Self> {
+        let (host, bit) = split_bit(path.as_ref()).ok_or(PathError::NoBitMember)?;
+        Ok(Self {
+            tag: EPath::parse_tag(host)?,
+            tag_type: None,
+            bit,
+        })
+    }
+
+    /// element of a BOOL array, `tag` is the array without index;
+    /// BOOL arrays are stored as DWORDs, so `index` maps to the DWORD element `index / 32`, bit `index % 32`
+    #[inline]
+    pub fn bool_array(tag: EPath, index: u32) -> Self {
+        Self {
+            tag: tag.with_element(index / BOOL_ARRAY_BITS),
+            tag_type: Some(TagType::Dword),
+            bit: (index % BOOL_ARRAY_BITS) as u8,
+        }
+    }
+
+    /// path of the host value
+    #[inline]
+    pub fn tag(&self) -> &EPath {
+        &self.tag
+    }
+
+    /// tag type of the host value; none if not resolved yet
+    #[inline]
+    pub fn tag_type(&self) -> Option<TagType> {
+        self.tag_type
+    }
+
+    /// bit position in the host value
+    #[inline]
+    pub fn bit(&self) -> u8 {
+        self.bit
+    }
+
+    /// number of bytes of the host value, also the mask width; none if not resolved yet
+    #[inline]
+    pub fn size(&self) -> Option<usize> {
+        self.tag_type.and_then(bit_host_size)
+    }
+
+    /// set tag type of the host value, eg. from a read reply; check the bit is in range
+    #[inline]
+    pub fn with_tag_type(self, tag_type: TagType) -> Result<Self> {
+        Self::new(self.tag, tag_type, self.bit)
+    }
+
+    /// set tag type of the host value from symbol or template info; check the bit is in range
+    pub fn with_symbol_type(self, symbol_type: SymbolType) -> Result<Self> {
+        let tag_type = symbol_type.tag_type().ok_or_else(|| {
+            ClientError::invalid_type(
+                format_args!("symbol type {:#06x}", symbol_type.0),
+                "integer tag type",
+            )
+        })?;
+        self.with_tag_type(tag_type)
+    }
+
+    /// get the bit from data of the host value
+    pub fn get(&self, data: &[u8]) -> Result<bool> {
+        let bit = self.bit as usize;
+        match data.get(bit / 8) {
+            Some(v) => Ok(v & (1 << (bit % 8)) != 0),
+            None => Err(Error::invalid_length(
+                data.len(),
+                format_args!("data of bit {}", bit),
+            )),
+        }
+    }
+}
+
+/// number of bytes of integer types that allow bit access
+#[inline]
+fn bit_host_size(tag_type: TagType) -> Option<usize> {
+    match tag_type {
+        TagType::Sint
+        | TagType::Usint
+        | TagType::Byte
+        | TagType::Int
+        | TagType::Uint
+        | TagType::Word
+        | TagType::Dint
+        | TagType::Udint
+        | TagType::Dword
+        | TagType::Lint
+        | TagType::Ulint
+        | TagType::Lword => tag_type.size(),
+        _ => None,
+    }
+}
+
+/// read the host value, then get the bit;
+/// the tag type of the host value is taken from the read reply if not known
+pub(crate) async fn ab_read_bit<C>(client: &mut C, req: BitRequest) -> Result<bool>
+where
+    C: MessageService<Error = ClientError>,
+{
+    let value: TagValue<Bytes> = ab_read_tag(client, req.tag.clone()).await?;
+    let req = match req.tag_type {
+        Some(_) => req,
+        None => req.with_tag_type(value.tag_type)?,
+    };
+    req.get(&value.value)
+}
+
+/// Read Modify Write Tag Service with masks of the host value width,
+/// so that other bits are kept untouched;
+/// the tag type of the host value must be known
+pub(crate) async fn ab_write_bit<C>(client: &mut C, req: BitRequest, value: bool) -> Result<()>
+where
+    C: MessageService<Error = ClientError>,
+{
+    let size = req
+        .size()
+        .ok_or_else(|| ClientError::invalid_value("no tag type", "tag type of the host value"))?;
+    let bit = req.bit as usize;
+    match size {
+        1 => {
+            let req = ReadModifyWriteRequest::<1>::new()
+                .tag(req.tag)
+                .bit(bit, value);
+            ab_read_modify_write(client, req).await
+        }
+        2 => {
+            let req = ReadModifyWriteRequest::<2>::new()
+                .tag(req.tag)
+                .bit(bit, value);
+            ab_read_modify_write(client, req).await
+        }
+        4 => {
+            let req = ReadModifyWriteRequest::<4>::new()
+                .tag(req.tag)
+                .bit(bit, value);
+            ab_read_modify_write(client, req).await
+        }
+        _ => {
+            let req = ReadModifyWriteRequest::<8>::new()
+                .tag(req.tag)
+                .bit(bit, value);
+            ab_read_modify_write(client, req).await
+        }
+    }
+}
+
+/// is the encoded value a single BOOL, eg. `TagValue { tag_type: TagType::Bool, value: true }`
+pub(crate) fn is_single_bool<D: Encode>(value: &D) -> Result<bool> {
+    // tag type | number of elements | BOOL
+    if value.bytes_count() != 5 {
+        return Ok(false);
+    }
+    let mut buf = LittleEndianEncoder::<ClientError>::new().encode_to_bytes(value)?;
+    Ok(buf.get_u16_le() == TagType::Bool.type_code() && buf.get_u16_le() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_bool_array() {
+        let req = BitRequest::bool_array(EPath::from_symbol("bools"), 37);
+        assert_eq!(req.tag(), &EPath::from_symbol("bools").with_element(1));
+        assert_eq!(req.bit(), 5);
+        assert_eq!(req.size(), Some(4));
+        assert!(req.get(&[]).is_err());
+        assert!(!req.get(&[0, 0b0010_0000, 0, 0]).unwrap());
+        assert!(req.get(&[0b0010_0000, 0, 0, 0]).unwrap());
+        assert!(!req.get(&[0b1101_1111, 0, 0, 0]).unwrap());
+
+        assert!(BitRequest::new(EPath::from_symbol("a"), TagType::Int, 16).is_err());
+        let err = BitRequest::new(EPath::from_symbol("a"), TagType::Real, 0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Decode);
+        let req = BitRequest::new(EPath::from_symbol("a"), TagType::Int, 9).unwrap();
+        assert!(req.get(&[0, 0b10]).unwrap());
+
+        let req = BitRequest::parse("Motor.Status.3").unwrap();
+        assert_eq!(
+            req.tag(),
+            &EPath::from_symbol("Motor").with_symbol("Status")
+        );
+        assert_eq!(req.bit(), 3);
+        assert_eq!(req.size(), None);
+        assert!(req.clone().with_tag_type(TagType::Sint).is_ok());
+        assert!(req.clone().with_tag_type(TagType::Real).is_err());
+        let req = req
+            .with_symbol_type(SymbolType::builder().atomic(0xC3).finish())
+            .unwrap();
+        assert_eq!(req.size(), Some(2));
+        let err = BitRequest::parse("Motor.Status").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Path);
+    }
+
+    #[test]
+    fn test_bit_masks() {
+        let mut req = ReadModifyWriteRequest::<2>::new()
+            .bit(9, true)
+            .bit(3, false);
+        assert_eq!(req.or_mask_mut(), &[0, 0b10]);
+        assert_eq!(req.and_mask_mut(), &[0b1111_0111, 0xFF]);
+    }
+
+    #[test]
+    fn test_is_single_bool() {
+        let value = TagValue {
+            tag_type: TagType::Bool,
+            value: true,
+        };
+        assert!(is_single_bool(&value).unwrap());
+        let value = TagValue {
+            tag_type: TagType::Sint,
+            value: 1_i8,
+        };
+        assert!(!is_single_bool(&value).unwrap());
+        let value = TagValue {
+            tag_type: TagType::Bool,
+            value: [true, false],
+        };
+        assert!(!is_single_bool(&value).unwrap());
+    }
+}
@@ -0,0 +1,136 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::symbol::{GetInstanceAttributeList, SymbolInstance};
+use crate::{cip::service::MessageService, ClientError, Result};
+use futures_util::{pin_mut, TryStreamExt};
+use std::borrow::Cow;
+
+/// user tags of the controller, grouped by scope
+#[derive(Debug, Clone, Default)]
+pub struct TagTree {
+    /// controller scope tags
+    pub controller: Vec<SymbolInstance<'static>>,
+    /// program scope tags
+    pub programs: Vec<ProgramTags>,
+}
+
+impl TagTree {
+    /// all tags, controller scope first
+    #[inline]
+    pub fn tags(&self) -> impl Iterator<Item = &SymbolInstance<'static>> {
+        self.controller
+            .iter()
+            .chain(self.programs.iter().flat_map(|v| v.tags.iter()))
+    }
+}
+
+/// tags under a program scope
+#[derive(Debug, Clone)]
+pub struct ProgramTags {
+    /// program symbol, eg. `Program:MainProgram`
+    pub program: SymbolInstance<'static>,
+    /// tags with fully qualified names, eg. `Program:MainProgram.my_tag`
+    pub tags: Vec<SymbolInstance<'static>>,
+}
+
+/// list controller scope tags, then tags of each program scope;
+/// system and module-defined symbols are excluded
+pub(crate) async fn ab_browse_tags<C>(client: &mut C) -> Result<TagTree>
+where
+    C: MessageService<Error = ClientError>,
+{
+    let mut res = TagTree::default();
+    let mut programs = Vec::new();
+    {
+        let stream = GetInstanceAttributeList::new(client).call();
+        pin_mut!(stream);
+        while let Some(item) = stream.try_next().await? {
+            if item.is_program() {
+                programs.push(item.into_owned());
+            } else if is_user_tag(&item) {
+                res.controller.push(item.into_owned());
+            }
+        }
+    }
+    for program in programs {
+        let mut tags = Vec::new();
+        {
+            let stream = GetInstanceAttributeList::new(client)
+                .program(program.name.as_ref())
+                .call();
+            pin_mut!(stream);
+            while let Some(item) = stream.try_next().await? {
+                if is_user_tag(&item) {
+                    let mut item = item.into_owned();
+                    item.name = Cow::Owned(format!("{}.{}", program.name, item.name));
+                    tags.push(item);
+                }
+            }
+        }
+        res.programs.push(ProgramTags { program, tags });
+    }
+    Ok(res)
+}
+
+#[inline]
+fn is_user_tag(item: &SymbolInstance<'_>) -> bool {
+    !item.is_module_defined() && !item.is_system()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        ab_eip::TagType,
+        mock::{MockDriver, MockEndpoint},
+        Client,
+    };
+
+    const SYMBOLS: &[(&str, u16, &str, u16)] = &[
+        ("", 0x01, "counter", 0x00C4),
+        ("", 0x02, "__hidden", 0x00C4),
+        ("", 0x03, "flags", 0x10C4), // system flag
+        ("", 0x04, "Local:1:I", 0x8F00),
+        ("", 0x05, "Program:Main", 0x1068),
+        ("", 0x106, "Program:Aux", 0x1068),
+        ("Program:Main", 0x01, "step", 0x00C4),
+        ("Program:Main", 0x02, "__hidden", 0x00C4),
+        ("Program:Aux", 0x01, "speed", 0x00CA),
+        ("Program:Aux", 0x02, "Local:2:O", 0x8F01),
+    ];
+
+    #[tokio::test]
+    async fn test_browse_tags() {
+        let endpoint = MockEndpoint {
+            symbols: SYMBOLS,
+            ..Default::default()
+        };
+        let mut client = Client::<MockDriver>::new(endpoint);
+        let tree = ab_browse_tags(&mut client).await.unwrap();
+        let names: Vec<_> = tree.controller.iter().map(|v| v.name.as_ref()).collect();
+        assert_eq!(names, ["counter"]);
+        let programs: Vec<_> = tree
+            .programs
+            .iter()
+            .map(|v| v.program.name.as_ref())
+            .collect();
+        assert_eq!(programs, ["Program:Main", "Program:Aux"]);
+        let names: Vec<_> = tree.tags().map(|v| v.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            [
+                "counter",
+                "Program:Main.step",
+                "Program:Aux.speed"
+            ]
+        );
+        assert_eq!(
+            tree.programs[1].tags[0].symbol_type.tag_type(),
+            Some(TagType::Real)
+        );
+    }
+}
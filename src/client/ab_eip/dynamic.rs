@@ -0,0 +1,789 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+//! dynamic tag values, decoded and encoded with template definitions at runtime
+
+use super::{
+    string::truncated_len,
+    symbol::SymbolType,
+    template::{MemberInfo, TemplateCache, TemplateDefinition},
+    AbService, TagRequest, TagType, TagValue,
+};
+use crate::{
+    cip::{epath::EPath, service::MessageService},
+    ClientError, Result,
+};
+use byteorder::{ByteOrder, LittleEndian};
+use bytes::{BufMut, Bytes, BytesMut};
+use core::convert::TryFrom;
+use rseip_core::{
+    codec::{BytesHolder, Encode, Encoder},
+    Error,
+};
+use std::collections::HashSet;
+
+/// dynamic tag value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// BOOL
+    Bool(bool),
+    /// SINT, 8-bit integer
+    Sint(i8),
+    /// INT, 16-bit integer
+    Int(i16),
+    /// DINT, 32-bit integer
+    Dint(i32),
+    /// LINT, 64-bit integer
+    Lint(i64),
+    /// USINT, unsigned 8-bit integer
+    Usint(u8),
+    /// UINT, unsigned 16-bit integer
+    Uint(u16),
+    /// UDINT, unsigned 32-bit integer
+    Udint(u32),
+    /// ULINT, unsigned 64-bit integer
+    Ulint(u64),
+    /// REAL, 32-bit float
+    Real(f32),
+    /// LREAL, 64-bit float
+    Lreal(f64),
+    /// string structure, eg. STRING
+    String(String),
+    /// array of values; BOOL arrays are expanded to bits
+    Array(Vec<Value>),
+    /// structure
+    Struct(StructValue),
+}
+
+impl Value {
+    /// get member by name if it's a structure
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Self::Struct(v) => v.get(name),
+            _ => None,
+        }
+    }
+}
+
+/// structure value
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    /// template name
+    pub name: String,
+    /// members, hidden members excluded
+    pub members: Vec<(String, Value)>,
+}
+
+impl StructValue {
+    /// get member by name
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.members.iter().find(|(k, _)| k == name).map(|(_, v)| v)
+    }
+
+    /// get mutable member by name
+    #[inline]
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.members
+            .iter_mut()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v)
+    }
+}
+
+/// encoded elements of [`Value`], to write with tag type
+#[derive(Debug, Clone)]
+pub struct EncodedValue {
+    count: u16,
+    data: Bytes,
+}
+
+impl EncodedValue {
+    /// number of elements
+    #[inline]
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+
+    /// encoded data bytes
+    #[inline]
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+}
+
+impl Encode for TagValue<EncodedValue> {
+    #[inline]
+    fn encode_by_ref<A: Encoder>(
+        &self,
+        buf: &mut BytesMut,
+        encoder: &mut A,
+    ) -> core::result::Result<(), A::Error> {
+        self.tag_type.encode_by_ref(buf, encoder)?;
+        encoder.encode_u16(self.value.count, buf)?;
+        buf.put_slice(&self.value.data);
+        Ok(())
+    }
+
+    #[inline]
+    fn bytes_count(&self) -> usize {
+        self.tag_type.bytes_count() + 2 + self.value.data.len()
+    }
+}
+
+/// decode tag values into [`Value`] and encode [`Value`] for writes with template definitions;
+/// templates are fetched through [`TemplateCache`] and kept for later use
+#[derive(Debug, Default)]
+pub struct ValueCodec {
+    templates: TemplateCache,
+}
+
+impl ValueCodec {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// use templates from the cache, eg. loaded from disk
+    #[inline]
+    pub fn with_templates(templates: TemplateCache) -> Self {
+        Self { templates }
+    }
+
+    /// template cache
+    #[inline]
+    pub fn templates(&self) -> &TemplateCache {
+        &self.templates
+    }
+
+    /// template cache, eg. to invalidate templates
+    #[inline]
+    pub fn templates_mut(&mut self) -> &mut TemplateCache {
+        &mut self.templates
+    }
+
+    /// take the template cache, eg. to persist it
+    #[inline]
+    pub fn into_templates(self) -> TemplateCache {
+        self.templates
+    }
+
+    /// fetch template definitions for the symbol type, including nested structures
+    pub async fn resolve<C>(&mut self, client: &mut C, symbol_type: SymbolType) -> Result<()>
+    where
+        C: MessageService<Error = ClientError>,
+    {
+        let mut pending: Vec<u16> = symbol_type.instance_id().into_iter().collect();
+        let mut visited = HashSet::new();
+        while let Some(instance_id) = pending.pop() {
+            if !visited.insert(instance_id) {
+                continue;
+            }
+            let item = self.templates.fetch(client, instance_id).await?;
+            pending.extend(
+                item.definition
+                    .members()
+                    .iter()
+                    .filter_map(|m| m.type_info.instance_id()),
+            );
+        }
+        Ok(())
+    }
+
+    /// read tag and decode it as [`Value`], template definitions fetched if not yet
+    pub async fn read_tag<C, P>(
+        &mut self,
+        client: &mut C,
+        req: P,
+        symbol_type: SymbolType,
+    ) -> Result<TagValue<Value>>
+    where
+        C: AbService + MessageService<Error = ClientError>,
+        P: Into<TagRequest> + Send + Sync,
+    {
+        self.resolve(client, symbol_type).await?;
+        let res: TagValue<BytesHolder> = client.read_tag_large(req).await?;
+        let data: Bytes = res.value.into();
+        self.decode(symbol_type, res.tag_type, &data[..])
+    }
+
+    /// decode tag value with resolved template definitions;
+    /// returns [`Value::Array`] if data contains more than one element
+    pub fn decode(
+        &self,
+        symbol_type: SymbolType,
+        tag_type: TagType,
+        data: &[u8],
+    ) -> Result<TagValue<Value>> {
+        if let Some(def) = self.struct_def(symbol_type)? {
+            if tag_type.structure_handle() != Some(def.handle) {
+                return Err(Error::invalid_type(
+                    format_args!("tag type {:?}", tag_type),
+                    format_args!("structure {}", def.name),
+                ));
+            }
+        }
+        let size = self.size_of(symbol_type)?;
+        if data.is_empty() || size == 0 || !data.len().is_multiple_of(size) {
+            return Err(Error::invalid_length(
+                data.len(),
+                format_args!("multiple of element size {}", size),
+            ));
+        }
+        let value = if data.len() == size && symbol_type.type_code() != Some(0xD3) {
+            self.decode_element(symbol_type, data)?
+        } else {
+            self.decode_array(symbol_type, data)?
+        };
+        Ok(TagValue { tag_type, value })
+    }
+
+    /// encode value and write it to the tag, template definitions fetched if not yet
+    pub async fn write_tag<C>(
+        &mut self,
+        client: &mut C,
+        tag: EPath,
+        symbol_type: SymbolType,
+        value: &Value,
+    ) -> Result<()>
+    where
+        C: AbService + MessageService<Error = ClientError>,
+    {
+        self.resolve(client, symbol_type).await?;
+        let value = self.encode(symbol_type, value)?;
+        client.write_tag_large(tag, value).await
+    }
+
+    /// encode value with resolved template definitions;
+    /// [`Value::Array`] is encoded as elements of the symbol type
+    pub fn encode(&self, symbol_type: SymbolType, value: &Value) -> Result<TagValue<EncodedValue>> {
+        let tag_type = match self.struct_def(symbol_type)? {
+            Some(def) => TagType::Structure(def.handle),
+            None => atomic_tag_type(symbol_type.type_code().unwrap_or_default())?,
+        };
+        let size = self.size_of(symbol_type)?;
+        let (count, len) = match value {
+            Value::Array(items) if symbol_type.type_code() == Some(0xD3) => {
+                if !items.len().is_multiple_of(32) {
+                    return Err(Error::invalid_length(items.len(), "multiple of 32 bits"));
+                }
+                (items.len() / 32, items.len() / 8)
+            }
+            Value::Array(items) => (items.len(), items.len() * size),
+            _ => (1, size),
+        };
+        if count == 0 || count > u16::MAX as usize {
+            return Err(Error::invalid_length(count, "1 to 65535 elements"));
+        }
+        let mut buf = vec![0; len];
+        match value {
+            Value::Array(items) => self.encode_array(symbol_type, items, &mut buf)?,
+            _ => self.encode_element(symbol_type, value, &mut buf)?,
+        }
+        Ok(TagValue {
+            tag_type,
+            value: EncodedValue {
+                count: count as u16,
+                data: buf.into(),
+            },
+        })
+    }
+
+    fn struct_def(&self, symbol_type: SymbolType) -> Result<Option<&TemplateDefinition>> {
+        match symbol_type.instance_id() {
+            Some(instance_id) => match self.templates.get(instance_id) {
+                Some(item) => Ok(Some(&item.definition)),
+                None => Err(Error::invalid_value(
+                    format_args!("template {:#0x}", instance_id),
+                    "resolved template",
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// size of one element in bytes
+    fn size_of(&self, symbol_type: SymbolType) -> Result<usize> {
+        if let Some(def) = self.struct_def(symbol_type)? {
+            return Ok(def.struct_size as usize);
+        }
+        let code = symbol_type.type_code().unwrap_or_default();
+        atomic_size(code).ok_or_else(|| unsupported_type_error(code))
+    }
+
+    fn decode_element(&self, symbol_type: SymbolType, buf: &[u8]) -> Result<Value> {
+        match self.struct_def(symbol_type)? {
+            Some(def) => self.decode_struct(def, buf),
+            None => decode_atomic(symbol_type.type_code().unwrap_or_default(), buf),
+        }
+    }
+
+    fn decode_array(&self, symbol_type: SymbolType, buf: &[u8]) -> Result<Value> {
+        let size = self.size_of(symbol_type)?;
+        let mut items = Vec::with_capacity(buf.len() / size);
+        for chunk in buf.chunks_exact(size) {
+            match self.decode_element(symbol_type, chunk)? {
+                // BOOL array
+                Value::Array(bits) => items.extend(bits),
+                v => items.push(v),
+            }
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn decode_struct(&self, def: &TemplateDefinition, buf: &[u8]) -> Result<Value> {
+        let buf = slice(buf, 0, def.struct_size as usize)?;
+        if def.string_capacity().is_some() {
+            return self.decode_string(def, buf);
+        }
+        let mut members = Vec::with_capacity(def.members().len());
+        for member in def.visible_members() {
+            let value = self.decode_member(member, buf)?;
+            members.push((member.name.clone(), value));
+        }
+        Ok(Value::Struct(StructValue {
+            name: def.name.clone(),
+            members,
+        }))
+    }
+
+    fn decode_member(&self, member: &MemberInfo, buf: &[u8]) -> Result<Value> {
+        let offset = member.offset as usize;
+        if member.type_info.is_bool() {
+            // array_size is bit location of the host member
+            let bit = member.array_size as usize;
+            let byte = slice(buf, offset + bit / 8, 1)?[0];
+            return Ok(Value::Bool(byte & (1 << (bit % 8)) != 0));
+        }
+        let size = self.size_of(member.type_info)?;
+        if member.array_size == 0 {
+            self.decode_element(member.type_info, slice(buf, offset, size)?)
+        } else {
+            let len = size * member.array_size as usize;
+            self.decode_array(member.type_info, slice(buf, offset, len)?)
+        }
+    }
+
+    fn decode_string(&self, def: &TemplateDefinition, buf: &[u8]) -> Result<Value> {
+        let (len, data) = string_members(def)?;
+        let len = LittleEndian::read_i32(slice(buf, len.offset as usize, 4)?);
+        let len = len.clamp(0, data.array_size as i32) as usize;
+        let data = slice(buf, data.offset as usize, len)?;
+        Ok(Value::String(String::from_utf8_lossy(data).into_owned()))
+    }
+
+    fn encode_element(&self, symbol_type: SymbolType, value: &Value, buf: &mut [u8]) -> Result<()> {
+        match self.struct_def(symbol_type)? {
+            Some(def) => self.encode_struct(def, value, buf),
+            None => encode_atomic(symbol_type.type_code().unwrap_or_default(), value, buf),
+        }
+    }
+
+    fn encode_array(&self, symbol_type: SymbolType, items: &[Value], buf: &mut [u8]) -> Result<()> {
+        if symbol_type.type_code() == Some(0xD3) {
+            // BOOL array, packed into DWORDs
+            if items.len() != buf.len() * 8 {
+                return Err(array_len_error(buf.len() * 8, items.len()));
+            }
+            for (i, item) in items.iter().enumerate() {
+                set_bit(buf, i, to_bool(item)?);
+            }
+            return Ok(());
+        }
+        let size = self.size_of(symbol_type)?;
+        if items.len() * size != buf.len() {
+            return Err(array_len_error(buf.len() / size, items.len()));
+        }
+        for (item, chunk) in items.iter().zip(buf.chunks_exact_mut(size)) {
+            self.encode_element(symbol_type, item, chunk)?;
+        }
+        Ok(())
+    }
+
+    fn encode_struct(&self, def: &TemplateDefinition, value: &Value, buf: &mut [u8]) -> Result<()> {
+        let buf = slice_mut(buf, 0, def.struct_size as usize)?;
+        if def.string_capacity().is_some() {
+            return match value {
+                Value::String(v) => self.encode_string(def, v, buf),
+                _ => Err(mismatch_error(&def.name, value)),
+            };
+        }
+        let value = match value {
+            Value::Struct(v) => v,
+            _ => return Err(mismatch_error(&def.name, value)),
+        };
+        if let Some((name, _)) = value.members.iter().find(|(k, _)| def.member(k).is_none()) {
+            return Err(Error::invalid_value(
+                format_args!("member {}", name),
+                format_args!("member of {}", def.name),
+            ));
+        }
+        for member in def.visible_members() {
+            let item = value.get(&member.name).ok_or_else(|| {
+                ClientError::invalid_value(
+                    format_args!("missing member {}", member.name),
+                    format_args!("all members of {}", def.name),
+                )
+            })?;
+            self.encode_member(member, item, buf)?;
+        }
+        Ok(())
+    }
+
+    fn encode_member(&self, member: &MemberInfo, value: &Value, buf: &mut [u8]) -> Result<()> {
+        let offset = member.offset as usize;
+        if member.type_info.is_bool() {
+            // array_size is bit location of the host member
+            let bit = member.array_size as usize;
+            let host = slice_mut(buf, offset + bit / 8, 1)?;
+            set_bit(host, bit % 8, to_bool(value)?);
+            return Ok(());
+        }
+        let size = self.size_of(member.type_info)?;
+        if member.array_size == 0 {
+            self.encode_element(member.type_info, value, slice_mut(buf, offset, size)?)
+        } else {
+            let len = size * member.array_size as usize;
+            match value {
+                Value::Array(items) => {
+                    self.encode_array(member.type_info, items, slice_mut(buf, offset, len)?)
+                }
+                _ => Err(mismatch_error(&member.name, value)),
+            }
+        }
+    }
+
+    fn encode_string(&self, def: &TemplateDefinition, value: &str, buf: &mut [u8]) -> Result<()> {
+        let (len, data) = string_members(def)?;
+        // truncated if too long
+        let bytes = &value.as_bytes()[..truncated_len(value, data.array_size as usize)];
+        LittleEndian::write_i32(slice_mut(buf, len.offset as usize, 4)?, bytes.len() as i32);
+        slice_mut(buf, data.offset as usize, bytes.len())?.copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// size of atomic type in bytes
+#[inline]
+fn atomic_size(type_code: u8) -> Option<usize> {
+    TagType::from_type_code(type_code as u16).and_then(|v| v.size())
+}
+
+fn decode_atomic(type_code: u8, buf: &[u8]) -> Result<Value> {
+    let v = match type_code {
+        0xC1 => Value::Bool(buf[0] != 0),
+        0xC2 => Value::Sint(buf[0] as i8),
+        // INT, ITIME
+        0xC3 | 0xD8 => Value::Int(LittleEndian::read_i16(buf)),
+        // DINT, STIME, FTIME, TIME
+        0xC4 | 0xCC | 0xD6 | 0xDB => Value::Dint(LittleEndian::read_i32(buf)),
+        // LINT, LTIME
+        0xC5 | 0xD7 => Value::Lint(LittleEndian::read_i64(buf)),
+        0xC6 | 0xD1 => Value::Usint(buf[0]),
+        // UINT, DATE, WORD, ENGUNIT
+        0xC7 | 0xCD | 0xD2 | 0xDD => Value::Uint(LittleEndian::read_u16(buf)),
+        // UDINT, TIME_OF_DAY
+        0xC8 | 0xCE => Value::Udint(LittleEndian::read_u32(buf)),
+        0xC9 | 0xD4 => Value::Ulint(LittleEndian::read_u64(buf)),
+        0xCA => Value::Real(LittleEndian::read_f32(buf)),
+        0xCB => Value::Lreal(LittleEndian::read_f64(buf)),
+        0xD3 => {
+            // 32-bit BOOL array
+            let bits = LittleEndian::read_u32(buf);
+            Value::Array((0..32).map(|i| Value::Bool(bits & (1 << i) != 0)).collect())
+        }
+        _ => return Err(unsupported_type_error(type_code)),
+    };
+    Ok(v)
+}
+
+/// tag type for writes
+#[inline]
+fn atomic_tag_type(type_code: u8) -> Result<TagType> {
+    TagType::from_type_code(type_code as u16).ok_or_else(|| unsupported_type_error(type_code))
+}
+
+fn encode_atomic(type_code: u8, value: &Value, buf: &mut [u8]) -> Result<()> {
+    match type_code {
+        0xC1 => buf[0] = if to_bool(value)? { 0xFF } else { 0 },
+        0xC2 => buf[0] = to_int::<i8>(type_code, value)? as u8,
+        0xC3 | 0xD8 => LittleEndian::write_i16(buf, to_int(type_code, value)?),
+        0xC4 | 0xCC | 0xD6 | 0xDB => LittleEndian::write_i32(buf, to_int(type_code, value)?),
+        0xC5 | 0xD7 => LittleEndian::write_i64(buf, to_int(type_code, value)?),
+        0xC6 | 0xD1 => buf[0] = to_int(type_code, value)?,
+        0xC7 | 0xCD | 0xD2 | 0xDD => LittleEndian::write_u16(buf, to_int(type_code, value)?),
+        0xC8 | 0xCE => LittleEndian::write_u32(buf, to_int(type_code, value)?),
+        0xC9 | 0xD4 => LittleEndian::write_u64(buf, to_int(type_code, value)?),
+        0xCA => LittleEndian::write_f32(buf, to_float(type_code, value)? as f32),
+        0xCB => LittleEndian::write_f64(buf, to_float(type_code, value)?),
+        0xD3 => match value {
+            Value::Array(items) if items.len() == 32 => {
+                for (i, item) in items.iter().enumerate() {
+                    set_bit(buf, i, to_bool(item)?);
+                }
+            }
+            Value::Array(items) => return Err(array_len_error(32, items.len())),
+            _ => LittleEndian::write_u32(buf, to_int(type_code, value)?),
+        },
+        _ => return Err(unsupported_type_error(type_code)),
+    }
+    Ok(())
+}
+
+#[inline]
+fn to_bool(value: &Value) -> Result<bool> {
+    match value {
+        Value::Bool(v) => Ok(*v),
+        _ => Err(mismatch_error("BOOL", value)),
+    }
+}
+
+/// integers of any size, range checked
+fn to_int<T: TryFrom<i128>>(type_code: u8, value: &Value) -> Result<T> {
+    let v = match value {
+        Value::Sint(v) => *v as i128,
+        Value::Int(v) => *v as i128,
+        Value::Dint(v) => *v as i128,
+        Value::Lint(v) => *v as i128,
+        Value::Usint(v) => *v as i128,
+        Value::Uint(v) => *v as i128,
+        Value::Udint(v) => *v as i128,
+        Value::Ulint(v) => *v as i128,
+        _ => return Err(mismatch_error(format_args!("{:#0x}", type_code), value)),
+    };
+    T::try_from(v).map_err(|_| {
+        Error::invalid_value(v, format_args!("value in range of type {:#0x}", type_code))
+    })
+}
+
+/// floats or integers
+fn to_float(type_code: u8, value: &Value) -> Result<f64> {
+    match value {
+        Value::Real(v) => Ok(*v as f64),
+        Value::Lreal(v) => Ok(*v),
+        _ => to_int::<i64>(type_code, value).map(|v| v as f64),
+    }
+}
+
+#[inline]
+fn set_bit(buf: &mut [u8], bit: usize, value: bool) {
+    let mask = 1 << (bit % 8);
+    if value {
+        buf[bit / 8] |= mask;
+    } else {
+        buf[bit / 8] &= !mask;
+    }
+}
+
+#[inline]
+fn mismatch_error(expected: impl core::fmt::Display, value: &Value) -> ClientError {
+    Error::invalid_type(format_args!("{:?}", value), expected)
+}
+
+#[inline]
+fn array_len_error(expected: usize, actual: usize) -> ClientError {
+    Error::invalid_length(actual, format_args!("{} elements", expected))
+}
+
+#[inline]
+fn unsupported_type_error(type_code: u8) -> ClientError {
+    Error::invalid_type(
+        format_args!("type code {:#0x}", type_code),
+        "supported type",
+    )
+}
+
+/// `LEN` and `DATA` members of a string structure
+#[inline]
+fn string_members(def: &TemplateDefinition) -> Result<(&MemberInfo, &MemberInfo)> {
+    match (def.member("LEN"), def.member("DATA")) {
+        (Some(len), Some(data)) => Ok((len, data)),
+        _ => Err(Error::invalid_value(
+            format_args!("structure {}", def.name),
+            "string structure with LEN and DATA",
+        )),
+    }
+}
+
+#[inline]
+fn slice_mut(buf: &mut [u8], offset: usize, len: usize) -> Result<&mut [u8]> {
+    let size = buf.len();
+    buf.get_mut(offset..offset + len)
+        .ok_or_else(|| Error::invalid_length(size, format_args!("at least {}", offset + len)))
+}
+
+#[inline]
+fn slice(buf: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    buf.get(offset..offset + len)
+        .ok_or_else(|| Error::invalid_length(buf.len(), format_args!("at least {}", offset + len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ab_eip::template::Template;
+
+    fn member(name: &str, type_info: SymbolType, array_size: u16, offset: u32) -> MemberInfo {
+        MemberInfo {
+            name: name.to_owned(),
+            array_size,
+            type_info,
+            offset,
+        }
+    }
+
+    fn atomic(type_code: u8) -> SymbolType {
+        SymbolType::builder().atomic(type_code).finish()
+    }
+
+    fn insert(cache: &mut TemplateCache, instance_id: u16, definition: TemplateDefinition) {
+        let template = Template {
+            instance_id,
+            handle: definition.handle,
+            member_count: definition.members.len() as u16,
+            object_size: 0,
+            struct_size: definition.struct_size,
+        };
+        cache.insert(template, definition);
+    }
+
+    fn codec() -> ValueCodec {
+        let mut cache = TemplateCache::new();
+        insert(
+            &mut cache,
+            0x100,
+            TemplateDefinition {
+                name: "STRING".to_owned(),
+                handle: 0x0FCE,
+                struct_size: 88,
+                members: vec![
+                    member("LEN", atomic(0xC4), 0, 0),
+                    member("DATA", atomic(0xC2), 82, 4),
+                ],
+            },
+        );
+        insert(
+            &mut cache,
+            0x200,
+            TemplateDefinition {
+                name: "MyUDT".to_owned(),
+                handle: 0x1234,
+                struct_size: 104,
+                members: vec![
+                    member("a", atomic(0xC4), 0, 0),
+                    member("ZZZZZZZZZZMyUDT1", atomic(0xC2), 0, 4),
+                    member("b", atomic(0xC1), 0, 4),
+                    member("c", atomic(0xC1), 2, 4),
+                    member("d", atomic(0xCA), 2, 8),
+                    member("s", SymbolType::builder().structure(0x100).finish(), 0, 16),
+                ],
+            },
+        );
+        ValueCodec::with_templates(cache)
+    }
+
+    #[test]
+    fn test_decode_struct() {
+        let codec = codec();
+        let mut data = vec![0u8; 104];
+        data[0..4].copy_from_slice(&(-5i32).to_le_bytes());
+        data[4] = 0b100;
+        data[8..12].copy_from_slice(&1.5f32.to_le_bytes());
+        data[12..16].copy_from_slice(&2.5f32.to_le_bytes());
+        data[16..20].copy_from_slice(&3i32.to_le_bytes());
+        data[20..25].copy_from_slice(b"abcde");
+
+        let symbol_type = SymbolType::builder().structure(0x200).finish();
+        let res = codec
+            .decode(symbol_type, TagType::Structure(0x1234), &data)
+            .unwrap();
+        let value = res.value;
+        assert_eq!(value.get("a"), Some(&Value::Dint(-5)));
+        assert_eq!(value.get("b"), Some(&Value::Bool(false)));
+        assert_eq!(value.get("c"), Some(&Value::Bool(true)));
+        assert_eq!(
+            value.get("d"),
+            Some(&Value::Array(vec![Value::Real(1.5), Value::Real(2.5)]))
+        );
+        assert_eq!(value.get("s"), Some(&Value::String("abc".to_owned())));
+        assert!(value.get("ZZZZZZZZZZMyUDT1").is_none());
+
+        let res = codec.decode(symbol_type, TagType::Structure(0x1), &data);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_encode_struct() {
+        let codec = codec();
+        let symbol_type = SymbolType::builder().structure(0x200).finish();
+        let mut data = vec![0u8; 104];
+        data[0..4].copy_from_slice(&(-5i32).to_le_bytes());
+        data[4] = 0b101;
+        data[8..12].copy_from_slice(&1.5f32.to_le_bytes());
+        data[12..16].copy_from_slice(&2.5f32.to_le_bytes());
+        data[16..20].copy_from_slice(&3i32.to_le_bytes());
+        data[20..23].copy_from_slice(b"abc");
+        let value = codec
+            .decode(symbol_type, TagType::Structure(0x1234), &data)
+            .unwrap()
+            .value;
+
+        let res = codec.encode(symbol_type, &value).unwrap();
+        assert_eq!(res.tag_type, TagType::Structure(0x1234));
+        assert_eq!(res.value.count(), 1);
+        assert_eq!(&res.value.data()[..], &data[..]);
+
+        // integers are range checked
+        let mut value = value;
+        if let Value::Struct(v) = &mut value {
+            *v.get_mut("a").unwrap() = Value::Lint(i64::MAX);
+        }
+        assert!(codec.encode(symbol_type, &value).is_err());
+
+        let bits: Vec<_> = (0..64).map(|i| Value::Bool(i % 3 == 0)).collect();
+        let res = codec.encode(atomic(0xD3), &Value::Array(bits)).unwrap();
+        assert_eq!(res.value.count(), 2);
+        assert_eq!(&res.value.data()[..4], &[0x49, 0x92, 0x24, 0x49]);
+    }
+
+    #[test]
+    fn test_decode_atomic() {
+        let codec = codec();
+        let res = codec
+            .decode(atomic(0xC3), TagType::Int, &[1, 0, 2, 0])
+            .unwrap();
+        assert_eq!(res.value, Value::Array(vec![Value::Int(1), Value::Int(2)]));
+
+        let res = codec
+            .decode(atomic(0xD3), TagType::Dword, &[1, 0, 0, 0x80])
+            .unwrap();
+        match res.value {
+            Value::Array(bits) => {
+                assert_eq!(bits.len(), 32);
+                assert_eq!(bits[0], Value::Bool(true));
+                assert_eq!(bits[1], Value::Bool(false));
+                assert_eq!(bits[31], Value::Bool(true));
+            }
+            _ => panic!("expect array"),
+        }
+    }
+
+    #[test]
+    fn test_string_members() {
+        let codec = codec();
+        let def = TemplateDefinition {
+            name: "NotString".to_owned(),
+            handle: 0x1,
+            struct_size: 4,
+            members: vec![member("LEN", atomic(0xC4), 0, 0)],
+        };
+        let err = codec.decode_string(&def, &[0; 4]).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Decode);
+        let err = codec.encode_string(&def, "a", &mut [0; 4]).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Decode);
+    }
+}
@@ -10,6 +10,10 @@ pub enum PathError {
     NameTooLong,
     NameParseError,
     Eof,
+    /// not terminated with a bit member
+    NoBitMember,
+    /// BOOL written to a bit member, the host type is not known
+    BitMember(u8),
 }
 
 impl fmt::Debug for PathError {
@@ -22,6 +26,12 @@ impl fmt::Debug for PathError {
             Self::NameTooLong => write!(f, "syntax error - name too long"),
             Self::NameParseError => write!(f, "syntax error - parse name failure"),
             Self::Eof => write!(f, "syntax error - unexpected end of input buffer"),
+            Self::NoBitMember => write!(f, "syntax error - not terminated with a bit member"),
+            Self::BitMember(bit) => write!(
+                f,
+                "bit member {} - write it with write_bit and the tag type of the host value",
+                bit
+            ),
         }
     }
 }
@@ -72,8 +82,7 @@ impl PathParser for EPath {
                             if !buf.is_empty() {
                                 return Err(PathError::SyntaxError);
                             }
-                            // 0-7 or 0-31 if DWORD
-                            if num >= 32 {
+                            if !is_bit_member(num) {
                                 return Err(PathError::SyntaxError);
                             }
                             res.push(Segment::Element(num));
@@ -92,6 +101,73 @@ impl PathParser for EPath {
     }
 }
 
+/// tag path of [`AbService::write_tag`](super::AbService::write_tag),
+/// either a parsed [`EPath`] or a tag path string which may be terminated with a bit member
+pub trait WritePath: Send + Sync {
+    /// parsed path, and the bit if terminated with a bit member
+    fn into_write_path(self) -> Result<(EPath, Option<u8>), PathError>;
+}
+
+impl WritePath for EPath {
+    /// a trailing element after a member is taken as a bit member, eg. `Motor.Status.3`;
+    /// `Motor.Status[3]` parses to the same path
+    #[inline]
+    fn into_write_path(self) -> Result<(EPath, Option<u8>), PathError> {
+        let bit = trailing_bit(&self);
+        Ok((self, bit))
+    }
+}
+
+impl WritePath for &str {
+    #[inline]
+    fn into_write_path(self) -> Result<(EPath, Option<u8>), PathError> {
+        let path = EPath::parse_tag(self)?;
+        Ok((path, split_bit(self.as_bytes()).map(|(_, bit)| bit)))
+    }
+}
+
+impl WritePath for String {
+    #[inline]
+    fn into_write_path(self) -> Result<(EPath, Option<u8>), PathError> {
+        self.as_str().into_write_path()
+    }
+}
+
+/// split bit-terminated tag path into host path and bit, eg. `a.b.3` into `a.b` and `3`;
+/// none if not terminated with a bit member
+pub(crate) fn split_bit(path: &[u8]) -> Option<(&[u8], u8)> {
+    let pos = path.iter().rposition(|c| *c == b'.')?;
+    let (host, bit) = (&path[..pos], &path[pos + 1..]);
+    if host.is_empty() || bit.is_empty() || bit.len() > 2 || !bit.iter().all(|c| is_digit(*c)) {
+        return None;
+    }
+    // safety: all digits
+    let bit: u8 = unsafe { str::from_utf8_unchecked(bit) }.parse().ok()?;
+    if is_bit_member(bit as u32) {
+        Some((host, bit))
+    } else {
+        None
+    }
+}
+
+/// bit of a trailing element after a member, eg. `Symbol(a), Symbol(b), Element(3)`
+fn trailing_bit(path: &EPath) -> Option<u8> {
+    match &path[..] {
+        [Segment::Symbol(_), .., Segment::Symbol(_), Segment::Element(num)]
+            if is_bit_member(*num) =>
+        {
+            Some(*num as u8)
+        }
+        _ => None,
+    }
+}
+
+/// 0-7 if SINT, 0-31 if DINT or DWORD, 0-63 if LINT
+#[inline]
+const fn is_bit_member(num: u32) -> bool {
+    num < 64
+}
+
 #[inline]
 fn parse_symbol_and_optional_numbers(
     buf: &mut &[u8],
@@ -328,4 +404,55 @@ mod tests {
             assert!(res.is_err());
         }
     }
+
+    #[test]
+    fn test_split_bit() {
+        assert_eq!(
+            split_bit(b"Motor.Status.3"),
+            Some((&b"Motor.Status"[..], 3))
+        );
+        assert_eq!(split_bit(b"a[1].63"), Some((&b"a[1]"[..], 63)));
+        assert_eq!(split_bit(b"a.64"), None);
+        assert_eq!(split_bit(b"a.b"), None);
+        assert_eq!(split_bit(b"a[3]"), None);
+        assert_eq!(split_bit(b".3"), None);
+    }
+
+    #[test]
+    fn test_bit_member_range() {
+        let path = EPath::parse_tag("a.b.63").unwrap();
+        assert_eq!(
+            path,
+            EPath::from_symbol("a").with_symbol("b").with_element(63)
+        );
+        assert!(EPath::parse_tag("a.b.64").is_err());
+        assert_eq!(split_bit(b"a.b.63"), Some((&b"a.b"[..], 63)));
+        assert_eq!(split_bit(b"a.b.64"), None);
+    }
+
+    #[test]
+    fn test_write_path() {
+        let (path, bit) = "Motor.Status.3".into_write_path().unwrap();
+        assert_eq!(
+            path,
+            EPath::from_symbol("Motor")
+                .with_symbol("Status")
+                .with_element(3)
+        );
+        assert_eq!(bit, Some(3));
+        let (_, bit) = "Motor.Status[3]".into_write_path().unwrap();
+        assert_eq!(bit, None);
+        let (_, bit) = "Motor[3]".into_write_path().unwrap();
+        assert_eq!(bit, None);
+
+        let path = EPath::parse_tag("Motor.Status.3").unwrap();
+        let (_, bit) = path.into_write_path().unwrap();
+        assert_eq!(bit, Some(3));
+        let path = EPath::parse_tag("Motor[3]").unwrap();
+        let (_, bit) = path.into_write_path().unwrap();
+        assert_eq!(bit, None);
+        let path = EPath::parse_tag("Motor.Status[64]").unwrap();
+        let (_, bit) = path.into_write_path().unwrap();
+        assert_eq!(bit, None);
+    }
 }
@@ -0,0 +1,465 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::{AbService, TagRequest, TagType, TagValue};
+use crate::{cip::epath::EPath, ClientError, Result, StdResult};
+use byteorder::{ByteOrder, LittleEndian};
+use bytes::Bytes;
+use core::fmt;
+use futures_util::{stream, Stream};
+use rseip_core::Error;
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+use tokio::time::{sleep_until, Instant};
+
+/// identify a registered tag of [`Poller`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TagId(usize);
+
+impl TagId {
+    /// index in the order of registration
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// tag to poll
+#[derive(Debug, Clone)]
+pub struct PollTag {
+    tag: EPath,
+    count: u16,
+    rate: Duration,
+    deadband: Option<f64>,
+}
+
+impl PollTag {
+    /// minimum rate, smaller rates are clamped to it
+    pub const MIN_RATE: Duration = Duration::from_millis(10);
+
+    /// poll `tag` every `rate`, at least [`PollTag::MIN_RATE`]
+    #[inline]
+    pub fn new(tag: EPath, rate: Duration) -> Self {
+        Self {
+            tag,
+            count: 1,
+            rate: rate.max(Self::MIN_RATE),
+            deadband: None,
+        }
+    }
+
+    /// number of elements, default 1
+    #[inline]
+    pub fn count(mut self, val: u16) -> Self {
+        self.count = val;
+        self
+    }
+
+    /// changes less than the deadband are not reported;
+    /// only applies to a single numeric value
+    #[inline]
+    pub fn deadband(mut self, val: f64) -> Self {
+        self.deadband = Some(val.abs());
+        self
+    }
+}
+
+/// tag value with the time it was read
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub value: TagValue<Bytes>,
+    pub timestamp: SystemTime,
+}
+
+/// value change of a polled tag
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub id: TagId,
+    pub tag: EPath,
+    /// last reported value, none for the first read
+    pub old: Option<Sample>,
+    pub new: Sample,
+}
+
+/// failed to read a polled tag
+#[derive(Debug)]
+pub struct PollError {
+    pub id: TagId,
+    pub tag: EPath,
+    pub err: ClientError,
+}
+
+impl fmt::Display for PollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "poll - failed to read {:?}: {}", self.tag, self.err)
+    }
+}
+
+impl std::error::Error for PollError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+/// tags of the same rate, read together
+#[derive(Debug)]
+struct RateGroup {
+    rate: Duration,
+    next: Instant,
+    ids: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    tag: PollTag,
+    last: Option<Sample>,
+    /// failed last time, errors are reported once until recovered
+    failed: bool,
+}
+
+/// polls registered tags by rate groups and reports value changes;
+/// tags of a rate group are read with as few Multiple Service Packets as possible
+///
+/// ```rust,ignore
+/// use futures_util::StreamExt;
+///
+/// let mut poller = Poller::new(client);
+/// let speed = poller.add(PollTag::new(EPath::parse_tag("speed")?, Duration::from_millis(100)).deadband(0.5));
+/// poller.add(PollTag::new(EPath::parse_tag("recipe")?, Duration::from_secs(10)));
+/// let stream = poller.into_stream();
+/// futures_util::pin_mut!(stream);
+/// while let Some(event) = stream.next().await {
+///     match event? {
+///         Ok(event) => println!("{:?}: {:?} -> {:?}", event.tag, event.old, event.new),
+///         Err(e) => println!("{}", e),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Poller<C> {
+    client: C,
+    entries: Vec<Entry>,
+    groups: Vec<RateGroup>,
+}
+
+impl<C: AbService> Poller<C> {
+    #[inline]
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            entries: Default::default(),
+            groups: Default::default(),
+        }
+    }
+
+    /// register tag, the first read is reported as a change
+    pub fn add(&mut self, tag: PollTag) -> TagId {
+        let id = self.entries.len();
+        match self.groups.iter_mut().find(|g| g.rate == tag.rate) {
+            Some(group) => group.ids.push(id),
+            None => self.groups.push(RateGroup {
+                rate: tag.rate,
+                next: Instant::now(),
+                ids: vec![id],
+            }),
+        }
+        self.entries.push(Entry {
+            tag,
+            last: None,
+            failed: false,
+        });
+        TagId(id)
+    }
+
+    /// last reported value of the tag
+    #[inline]
+    pub fn last(&self, id: TagId) -> Option<&Sample> {
+        self.entries.get(id.0).and_then(|v| v.last.as_ref())
+    }
+
+    #[inline]
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    #[inline]
+    pub fn client_mut(&mut self) -> &mut C {
+        &mut self.client
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> C {
+        self.client
+    }
+
+    /// wait for the next due rate group, poll it and return the events;
+    /// no events if nothing changed; a failed tag is reported once until recovered
+    pub async fn poll(&mut self) -> Result<Vec<StdResult<ChangeEvent, PollError>>> {
+        let idx = match self.groups.iter().enumerate().min_by_key(|(_, g)| g.next) {
+            Some((idx, _)) => idx,
+            None => return Err(Error::custom("poll - no tags registered")),
+        };
+        sleep_until(self.groups[idx].next).await;
+        let group = &mut self.groups[idx];
+        let now = Instant::now();
+        group.next += group.rate;
+        if group.next < now {
+            // missed ticks
+            group.next = now + group.rate;
+        }
+
+        let ids = group.ids.clone();
+        let requests: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                let tag = &self.entries[*id].tag;
+                TagRequest::new(tag.tag.clone()).count(tag.count)
+            })
+            .collect();
+        let replies = self.client.read_tags::<TagValue<Bytes>>(&requests).await?;
+        let timestamp = SystemTime::now();
+        let mut res = Vec::new();
+        for (id, reply) in ids.into_iter().zip(replies) {
+            let entry = &mut self.entries[id];
+            match reply {
+                Ok(value) => {
+                    entry.failed = false;
+                    if let Some(event) = entry.update(TagId(id), value, timestamp) {
+                        res.push(Ok(event));
+                    }
+                }
+                Err(err) if !entry.failed => {
+                    entry.failed = true;
+                    res.push(Err(PollError {
+                        id: TagId(id),
+                        tag: entry.tag.tag.clone(),
+                        err,
+                    }));
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(res)
+    }
+
+    /// keep polling, reports value changes and tag errors as a stream;
+    /// an error of the whole rate group is reported as the outer error and polling continues;
+    /// the stream ends immediately if no tags registered
+    pub fn into_stream(self) -> impl Stream<Item = Result<StdResult<ChangeEvent, PollError>>> {
+        let state = (self, VecDeque::new());
+        stream::unfold(state, |(mut poller, mut pending)| async move {
+            loop {
+                if let Some(item) = pending.pop_front() {
+                    return Some((item, (poller, pending)));
+                }
+                if poller.groups.is_empty() {
+                    return None;
+                }
+                match poller.poll().await {
+                    Ok(items) => pending.extend(items.into_iter().map(Ok)),
+                    Err(e) => pending.push_back(Err(e)),
+                }
+            }
+        })
+    }
+}
+
+impl Entry {
+    /// update the last value if changed
+    fn update(
+        &mut self,
+        id: TagId,
+        value: TagValue<Bytes>,
+        timestamp: SystemTime,
+    ) -> Option<ChangeEvent> {
+        if let Some(ref last) = self.last {
+            if !is_changed(&last.value, &value, self.tag.deadband) {
+                return None;
+            }
+        }
+        let new = Sample { value, timestamp };
+        let old = self.last.replace(new.clone());
+        Some(ChangeEvent {
+            id,
+            tag: self.tag.tag.clone(),
+            old,
+            new,
+        })
+    }
+}
+
+/// compare values, numeric values are compared with the deadband if any
+fn is_changed(old: &TagValue<Bytes>, new: &TagValue<Bytes>, deadband: Option<f64>) -> bool {
+    if let Some(deadband) = deadband {
+        if let (Some(a), Some(b)) = (numeric(old), numeric(new)) {
+            return (a - b).abs() >= deadband;
+        }
+    }
+    old != new
+}
+
+/// single numeric value
+fn numeric(value: &TagValue<Bytes>) -> Option<f64> {
+    let buf = &value.value[..];
+    if Some(buf.len()) != value.tag_type.size() {
+        return None;
+    }
+    let v = match value.tag_type {
+        TagType::Sint => buf[0] as i8 as f64,
+        TagType::Usint => buf[0] as f64,
+        TagType::Int => LittleEndian::read_i16(buf) as f64,
+        TagType::Uint => LittleEndian::read_u16(buf) as f64,
+        TagType::Dint => LittleEndian::read_i32(buf) as f64,
+        TagType::Udint => LittleEndian::read_u32(buf) as f64,
+        TagType::Lint => LittleEndian::read_i64(buf) as f64,
+        TagType::Ulint => LittleEndian::read_u64(buf) as f64,
+        TagType::Real => LittleEndian::read_f32(buf) as f64,
+        TagType::Lreal => LittleEndian::read_f64(buf),
+        _ => return None,
+    };
+    Some(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        mock::{MockDriver, MockEndpoint, MockTrace},
+        Client,
+    };
+    use std::sync::Arc;
+
+    fn real(v: f32) -> TagValue<Bytes> {
+        TagValue {
+            tag_type: TagType::Real,
+            value: Bytes::copy_from_slice(&v.to_le_bytes()),
+        }
+    }
+
+    fn dint(v: i32) -> TagValue<Bytes> {
+        TagValue {
+            tag_type: TagType::Dint,
+            value: Bytes::copy_from_slice(&v.to_le_bytes()),
+        }
+    }
+
+    /// values of the events: ok with tag name and the new value, or the tag name of the error
+    fn events(
+        items: Vec<StdResult<ChangeEvent, PollError>>,
+    ) -> Vec<StdResult<(String, TagValue<Bytes>), String>> {
+        items
+            .into_iter()
+            .map(|v| match v {
+                Ok(e) => Ok((format!("{:?}", e.tag), e.new.value)),
+                Err(e) => Err(format!("{:?}", e.tag)),
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll() {
+        let trace = Arc::new(MockTrace::default());
+        trace.set_value("a", Some(dint(1)));
+        trace.set_value("b", Some(real(1.0)));
+        trace.set_value("c", Some(dint(5)));
+        let endpoint = MockEndpoint {
+            trace: Some(trace.clone()),
+            ..Default::default()
+        };
+        let fast = Duration::from_millis(100);
+        let mut poller = Poller::new(Client::<MockDriver>::new(endpoint));
+        let a = poller.add(PollTag::new(EPath::from_symbol("a"), fast));
+        let b = poller.add(PollTag::new(EPath::from_symbol("b"), fast).deadband(0.5));
+        poller.add(PollTag::new(
+            EPath::from_symbol("c"),
+            Duration::from_secs(1),
+        ));
+        let tag = |name: &str| format!("{:?}", EPath::from_symbol(name));
+        let start = Instant::now();
+
+        // first reads are changes, tags of the same rate read in one packet
+        let res = events(poller.poll().await.unwrap());
+        assert_eq!(res, [Ok((tag("a"), dint(1))), Ok((tag("b"), real(1.0)))]);
+        let res = events(poller.poll().await.unwrap());
+        assert_eq!(res, [Ok((tag("c"), dint(5)))]);
+        assert_eq!(trace.take_reads(), [vec!["a", "b"], vec!["c"]]);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // within the deadband
+        trace.set_value("b", Some(real(1.4)));
+        assert!(poller.poll().await.unwrap().is_empty());
+        assert_eq!(start.elapsed(), fast);
+        trace.set_value("b", Some(real(1.5)));
+        let res = poller.poll().await.unwrap();
+        assert_eq!(res.len(), 1);
+        let event = res.into_iter().next().unwrap().unwrap();
+        assert_eq!(event.id, b);
+        assert_eq!(event.old.unwrap().value, real(1.0));
+        assert_eq!(poller.last(b).unwrap().value, real(1.5));
+
+        // error once per failure streak
+        trace.set_value("a", None);
+        let res = events(poller.poll().await.unwrap());
+        assert_eq!(res, [Err(tag("a"))]);
+        assert!(poller.poll().await.unwrap().is_empty());
+        trace.set_value("a", Some(dint(2)));
+        let res = events(poller.poll().await.unwrap());
+        assert_eq!(res, [Ok((tag("a"), dint(2)))]);
+        trace.set_value("a", None);
+        let res = events(poller.poll().await.unwrap());
+        assert_eq!(res, [Err(tag("a"))]);
+        assert_eq!(poller.last(a).unwrap().value, dint(2));
+        assert_eq!(start.elapsed(), fast * 6);
+        assert_eq!(trace.take_reads(), vec![vec!["a", "b"]; 6]);
+
+        // the slow group is due again, after the fast group of the same tick
+        for _ in 0..5 {
+            poller.poll().await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        let reads = trace.take_reads();
+        assert_eq!(reads.len(), 5);
+        assert_eq!(reads[3], ["a", "b"]);
+        assert_eq!(reads[4], ["c"]);
+    }
+
+    #[test]
+    fn test_deadband() {
+        let mut entry = Entry {
+            tag: PollTag::new(EPath::from_symbol("a"), Duration::from_secs(1)).deadband(0.5),
+            last: None,
+            failed: false,
+        };
+        let now = SystemTime::now();
+        let event = entry.update(TagId(0), real(1.0), now).unwrap();
+        assert!(event.old.is_none());
+        assert!(entry.update(TagId(0), real(1.4), now).is_none());
+        // compared with the last reported value
+        assert!(entry.update(TagId(0), real(0.6), now).is_none());
+        let event = entry.update(TagId(0), real(1.5), now).unwrap();
+        assert_eq!(event.old.unwrap().value, real(1.0));
+
+        // no deadband for non-numeric values
+        let a = TagValue {
+            tag_type: TagType::Structure(0x0FCE),
+            value: Bytes::from_static(&[1, 0]),
+        };
+        let mut b = a.clone();
+        assert!(!is_changed(&a, &b, Some(10.0)));
+        b.value = Bytes::from_static(&[1, 1]);
+        assert!(is_changed(&a, &b, Some(10.0)));
+    }
+
+    #[test]
+    fn test_min_rate() {
+        let tag = PollTag::new(EPath::from_symbol("a"), Duration::ZERO);
+        assert_eq!(tag.rate, PollTag::MIN_RATE);
+        let tag = PollTag::new(EPath::from_symbol("a"), Duration::from_secs(1));
+        assert_eq!(tag.rate, Duration::from_secs(1));
+    }
+}
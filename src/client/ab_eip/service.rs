@@ -4,11 +4,15 @@
 // Copyright: 2021, Joylei <leingliu@gmail.com>
 // License: MIT
 
+use super::bits::{ab_read_bit, ab_write_bit, is_single_bool, BitRequest};
+use super::browse::{ab_browse_tags, TagTree};
 use super::symbol::GetInstanceAttributeList;
 use super::*;
+use crate::cip::service::CommonServices;
 use crate::{client::ab_eip::interceptor::HasMoreInterceptor, StdResult};
-use bytes::{BufMut, BytesMut};
-use rseip_core::codec::{Encode, Encoder};
+use bytes::{Buf, BufMut, BytesMut};
+use core::ops::Range;
+use rseip_core::codec::{Decoder, Encode, Encoder, LittleEndianDecoder, LittleEndianEncoder};
 
 /// AB related operations
 #[async_trait::async_trait]
@@ -45,6 +49,22 @@ pub trait AbService: Send + Sync {
         P: Into<TagRequest> + Send + Sync,
         R: Decode<'de> + 'static;
 
+    /// Read Tag Service for multiple tags,
+    /// requests are packed into as many Multiple Service Packets as needed.
+    ///
+    /// Returns one result per tag, in the same order as `tags`
+    ///
+    /// ```rust,ignore
+    /// let tags = [
+    ///     TagRequest::new(EPath::parse_tag("tag1")?),
+    ///     TagRequest::new(EPath::parse_tag("tag2")?).count(10),
+    /// ];
+    /// let values: Vec<Result<TagValue<Bytes>>> = client.read_tags(&tags).await?;
+    /// ```
+    async fn read_tags<'de, R>(&mut self, tags: &[TagRequest]) -> Result<Vec<Result<R>>>
+    where
+        R: Decode<'de> + Send + 'static;
+
     /// Write Tag Service,
     /// CIP Data Table Write
     ///
@@ -66,10 +86,39 @@ pub trait AbService: Send + Sync {
     /// };
     /// client.write_tag(tag, value).await?;
     /// ```
-    async fn write_tag<D>(&mut self, tag: EPath, value: D) -> Result<()>
+    ///
+    /// ## bit members
+    /// a BOOL written to a bit-terminated path, eg. `Motor.Status.3`, is rejected
+    /// with [`PathError::BitMember`], so that the whole host value is not overwritten;
+    /// write it with [`AbService::write_bit`] and the tag type of the host value
+    /// ```rust,ignore
+    /// let req = BitRequest::parse("Motor.Status.3")?.with_tag_type(TagType::Dint)?;
+    /// client.write_bit(req, true).await?;
+    /// ```
+    async fn write_tag<P, D>(&mut self, tag: P, value: D) -> Result<()>
     where
+        P: WritePath,
         D: Encode + Send + Sync;
 
+    /// Write Tag Service for multiple tags,
+    /// requests are packed into as many Multiple Service Packets as needed.
+    ///
+    /// Returns the reply status of each tag, in the same order as `items`
+    ///
+    /// ```rust,ignore
+    /// let items = [
+    ///     WriteTagRequest::new(EPath::parse_tag("tag1")?, TagValue { tag_type: TagType::Dint, value: 1_i32 })?,
+    ///     WriteTagRequest::new(EPath::parse_tag("tag2")?, TagValue { tag_type: TagType::Real, value: 1.5_f32 })?,
+    /// ];
+    /// let res = client.write_tags(&items).await?;
+    /// for (item, status) in items.iter().zip(res) {
+    ///     if status.is_err() {
+    ///         println!("failed to write {:?}: {}", item.tag(), status);
+    ///     }
+    /// }
+    /// ```
+    async fn write_tags(&mut self, items: &[WriteTagRequest]) -> Result<Vec<Status>>;
+
     /// Read Tag Fragmented Service, enables client applications to read a tag
     /// with data that does not fit into a single packet (approximately 500 bytes)
     async fn read_tag_fragmented(
@@ -77,6 +126,18 @@ pub trait AbService: Send + Sync {
         req: ReadFragmentedRequest,
     ) -> Result<(bool, TagValue<Bytes>)>;
 
+    /// Read Tag Fragmented Service, keeps sending requests until all data received,
+    /// then decodes the reassembled data as `R`
+    ///
+    /// ```rust,ignore
+    /// let value: TagValue<MyUdt> = client.read_tag_large(tag).await?;
+    /// println!("{:?}",value);
+    /// ```
+    async fn read_tag_large<'de, P, R>(&mut self, req: P) -> Result<TagValue<R>>
+    where
+        P: Into<TagRequest> + Send + Sync,
+        R: Decode<'de> + 'static;
+
     /// Write Tag Fragmented Service, enables client applications to write to a tag
     /// in the controller whose data will not fit into a single packet (approximately 500 bytes)
     async fn write_tag_fragmented<D: Encode + Send + Sync>(
@@ -84,12 +145,49 @@ pub trait AbService: Send + Sync {
         req: WriteFragmentedRequest<D>,
     ) -> Result<bool>;
 
+    /// Write Tag Fragmented Service, encodes the value once, then splits it into
+    /// as many requests as needed to fit the message size limit;
+    /// chunk boundaries are rounded to whole elements where possible
+    ///
+    /// ```rust,ignore
+    /// let value = TagValue {
+    ///   tag_type: TagType::Structure(handle),
+    ///   value: items, // Vec<MyUdt>
+    /// };
+    /// client.write_tag_large(tag, value).await?;
+    /// ```
+    async fn write_tag_large<D>(&mut self, tag: EPath, value: TagValue<D>) -> Result<()>
+    where
+        D: Send + Sync,
+        TagValue<D>: Encode;
+
     /// Read Modify Write Tag Service, modifies Tag data with individual bit resolution
     async fn read_modify_write<const N: usize>(
         &mut self,
         req: ReadModifyWriteRequest<N>,
     ) -> Result<()>;
 
+    /// read a single bit of a BOOL array or an integer tag
+    ///
+    /// ```rust,ignore
+    /// let req = BitRequest::bool_array(EPath::parse_tag("bools")?, 37);
+    /// let value: bool = client.read_bit(req).await?;
+    /// ```
+    async fn read_bit(&mut self, req: BitRequest) -> Result<bool>;
+
+    /// write a single bit of a BOOL array or an integer tag
+    /// with Read Modify Write Tag Service, other bits are kept untouched;
+    /// masks are as wide as the host value, so its tag type must be known
+    ///
+    /// ```rust,ignore
+    /// let req = BitRequest::new(EPath::parse_tag("udt.flags")?, TagType::Dint, 3)?;
+    /// client.write_bit(req, true).await?;
+    ///
+    /// let req = BitRequest::parse("Motor.Status.3")?.with_tag_type(TagType::Int)?;
+    /// client.write_bit(req, true).await?;
+    /// ```
+    async fn write_bit(&mut self, req: BitRequest, value: bool) -> Result<()>;
+
     /// list tags
     ///
     /// ```rust,ignore
@@ -102,9 +200,14 @@ pub trait AbService: Send + Sync {
     /// })
     /// .await;
     /// ```
-    fn list_tag(&mut self) -> GetInstanceAttributeList<Self>
+    fn list_tag(&mut self) -> GetInstanceAttributeList<'_, Self>
     where
         Self: Sized;
+
+    /// list user tags of controller scope and all program scopes,
+    /// program scope tags are named as `Program:MainProgram.my_tag`;
+    /// system and module-defined symbols are excluded
+    async fn browse_tags(&mut self) -> Result<TagTree>;
 }
 
 macro_rules! impl_service {
@@ -123,17 +226,43 @@ macro_rules! impl_service {
                 Ok(res)
             }
 
+            /// Read Tag Service for multiple tags
+            #[inline]
+            async fn read_tags<'de, R>(&mut self, tags: &[TagRequest]) -> Result<Vec<Result<R>>>
+            where
+                R: Decode<'de> + Send + 'static,
+            {
+                let max_size = self.max_message_size();
+                let res = ab_read_tags(self, tags, max_size).await?;
+                Ok(res)
+            }
+
             /// Write Tag Service,
             /// CIP Data Table Write
             #[inline]
-            async fn write_tag<D>(&mut self, tag: EPath, value: D) -> Result<()>
+            async fn write_tag<P, D>(&mut self, tag: P, value: D) -> Result<()>
             where
+                P: WritePath,
                 D: Encode + Send + Sync,
             {
+                let (tag, bit) = tag.into_write_path()?;
+                if let Some(bit) = bit {
+                    if is_single_bool(&value)? {
+                        return Err(PathError::BitMember(bit).into());
+                    }
+                }
                 ab_write_tag(self, tag, value).await?;
                 Ok(())
             }
 
+            /// Write Tag Service for multiple tags
+            #[inline]
+            async fn write_tags(&mut self, items: &[WriteTagRequest]) -> Result<Vec<Status>> {
+                let max_size = self.max_message_size();
+                let res = ab_write_tags(self, items, max_size).await?;
+                Ok(res)
+            }
+
             /// Read Tag Fragmented Service
             #[inline]
             async fn read_tag_fragmented(
@@ -144,6 +273,18 @@ macro_rules! impl_service {
                 Ok(res)
             }
 
+            /// Read Tag Fragmented Service, keeps sending requests until all data received,
+            /// then decodes the reassembled data as `R`
+            #[inline]
+            async fn read_tag_large<'de, P, R>(&mut self, req: P) -> Result<TagValue<R>>
+            where
+                P: Into<TagRequest> + Send + Sync,
+                R: Decode<'de> + 'static,
+            {
+                let res = ab_read_tag_large(self, req).await?;
+                Ok(res)
+            }
+
             /// Write Tag Fragmented Service, enables client applications to write to a tag
             /// in the controller whose data will not fit into a single packet (approximately 500 bytes)
             #[inline]
@@ -155,6 +296,19 @@ macro_rules! impl_service {
                 Ok(res)
             }
 
+            /// Write Tag Fragmented Service, encodes the value once, then splits it into
+            /// as many requests as needed to fit the message size limit
+            #[inline]
+            async fn write_tag_large<D>(&mut self, tag: EPath, value: TagValue<D>) -> Result<()>
+            where
+                D: Send + Sync,
+                TagValue<D>: Encode,
+            {
+                let max_size = self.max_message_size();
+                ab_write_tag_large(self, tag, value, max_size).await?;
+                Ok(())
+            }
+
             /// Read Modify Write Tag Service, modifies Tag data with individual bit resolution
             #[inline]
             async fn read_modify_write<const N: usize>(
@@ -165,6 +319,19 @@ macro_rules! impl_service {
                 Ok(())
             }
 
+            /// read a single bit of a BOOL array or an integer tag
+            #[inline]
+            async fn read_bit(&mut self, req: BitRequest) -> Result<bool> {
+                ab_read_bit(self, req).await
+            }
+
+            /// write a single bit of a BOOL array or an integer tag
+            /// with Read Modify Write Tag Service
+            #[inline]
+            async fn write_bit(&mut self, req: BitRequest, value: bool) -> Result<()> {
+                ab_write_bit(self, req, value).await
+            }
+
             /// list tags
             ///
             /// ```rust,ignore
@@ -178,12 +345,20 @@ macro_rules! impl_service {
             /// .await;
             /// ```
             #[inline]
-            fn list_tag(&mut self) -> GetInstanceAttributeList<Self>
+            fn list_tag(&mut self) -> GetInstanceAttributeList<'_, Self>
             where
                 Self: Sized,
             {
                 GetInstanceAttributeList::new(self)
             }
+
+            /// list user tags of controller scope and all program scopes,
+            /// program scope tags are named as `Program:MainProgram.my_tag`;
+            /// system and module-defined symbols are excluded
+            #[inline]
+            async fn browse_tags(&mut self) -> Result<TagTree> {
+                ab_browse_tags(self).await
+            }
         }
     };
 }
@@ -191,22 +366,65 @@ macro_rules! impl_service {
 impl_service!(AbEipClient);
 impl_service!(AbEipConnection);
 impl_service!(MaybeConnected<AbEipDriver>);
+impl_service!(SharedClient);
+#[cfg(test)]
+impl_service!(crate::client::Client<crate::client::mock::MockDriver>);
 
 /// Read Tag Service,
 /// CIP Data Table Read
-async fn ab_read_tag<'de, C, P, R>(client: &mut C, req: P) -> Result<R>
+pub(super) async fn ab_read_tag<'de, C, P, R>(client: &mut C, req: P) -> Result<R>
 where
     C: MessageService<Error = ClientError>,
     P: Into<TagRequest> + Send + Sync,
     R: Decode<'de> + 'static,
 {
-    let req: TagRequest = req.into();
-    let mr = MessageRequest::new(SERVICE_READ_TAG, req.tag, req.count);
+    let TagRequest { tag, count, .. } = req.into();
+    let mr = MessageRequest::new(SERVICE_READ_TAG, tag, count);
     let resp: MessageReply<_> = client.send(mr).await?;
     resp.expect_service::<ClientError>(SERVICE_READ_TAG + REPLY_MASK)?;
     Ok(resp.data)
 }
 
+/// Read Tag Service for multiple tags, packed into Multiple Service Packets
+async fn ab_read_tags<'de, C, R>(
+    client: &mut C,
+    tags: &[TagRequest],
+    max_size: usize,
+) -> Result<Vec<Result<R>>>
+where
+    C: MessageService<Error = ClientError>,
+    R: Decode<'de> + Send + 'static,
+{
+    let batches = pack_batches(
+        tags.iter().map(|v| (v.request_size(), v.reply_size())),
+        max_size,
+    );
+    let mut res = Vec::with_capacity(tags.len());
+    for range in batches {
+        let items = &tags[range];
+        let mut iter = client
+            .multiple_service()
+            .push_all(
+                items
+                    .iter()
+                    .map(|v| MessageRequest::new(SERVICE_READ_TAG, &v.tag, v.count)),
+            )
+            .call()
+            .await?;
+        for i in 0..items.len() {
+            let item = match iter.next::<R>() {
+                Some(Ok(reply)) => reply
+                    .expect_service::<ClientError>(SERVICE_READ_TAG + REPLY_MASK)
+                    .map(|_| reply.data),
+                Some(Err(e)) => Err(e),
+                None => Err(Error::invalid_length(i, "a reply for each request")),
+            };
+            res.push(item);
+        }
+    }
+    Ok(res)
+}
+
 /// Write Tag Service,
 /// CIP Data Table Write
 async fn ab_write_tag<C, D>(client: &mut C, tag: EPath, value: D) -> Result<()>
@@ -220,6 +438,45 @@ where
     Ok(())
 }
 
+/// Write Tag Service for multiple tags, packed into Multiple Service Packets
+async fn ab_write_tags<C>(
+    client: &mut C,
+    items: &[WriteTagRequest],
+    max_size: usize,
+) -> Result<Vec<Status>>
+where
+    C: MessageService<Error = ClientError>,
+{
+    let batches = pack_batches(
+        items.iter().map(|v| (v.request_size(), v.reply_size())),
+        max_size,
+    );
+    let mut res = Vec::with_capacity(items.len());
+    for range in batches {
+        let items = &items[range];
+        let mut iter = client
+            .multiple_service()
+            .push_all(
+                items
+                    .iter()
+                    .map(|v| MessageRequest::new(SERVICE_WRITE_TAG, &v.tag, v.data.clone())),
+            )
+            .call()
+            .await?;
+        for i in 0..items.len() {
+            match iter.next_unchecked::<()>() {
+                Some(reply) => {
+                    let reply = reply?;
+                    reply.expect_service::<ClientError>(SERVICE_WRITE_TAG + REPLY_MASK)?;
+                    res.push(reply.status);
+                }
+                None => return Err(Error::invalid_length(i, "a reply for each request")),
+            }
+        }
+    }
+    Ok(res)
+}
+
 /// Read Tag Fragmented Service
 async fn ab_read_tag_fragmented<C>(
     client: &mut C,
@@ -239,6 +496,58 @@ where
     Ok((resp.0.status.has_more(), resp.0.data))
 }
 
+/// Read Tag Fragmented Service, keeps sending requests until all data received
+async fn ab_read_tag_large<'de, C, P, R>(client: &mut C, req: P) -> Result<TagValue<R>>
+where
+    C: MessageService<Error = ClientError>,
+    P: Into<TagRequest> + Send + Sync,
+    R: Decode<'de> + 'static,
+{
+    let TagRequest { tag, count, .. } = req.into();
+    let mut buf = BytesMut::new();
+    let mut tag_type: Option<TagType> = None;
+    loop {
+        if buf.len() > u16::MAX as usize {
+            return Err(Error::invalid_length(
+                buf.len(),
+                format_args!("data offset within u16 reading tag {:?}", tag),
+            ));
+        }
+        let offset = buf.len();
+        let req = ReadFragmentedRequest::new()
+            .tag(tag.clone())
+            .count(count)
+            .offset(offset as u16);
+        let (has_more, value) = ab_read_tag_fragmented(client, req).await?;
+        match tag_type {
+            Some(v) if v != value.tag_type => {
+                return Err(Error::invalid_type(
+                    format_args!("tag type {:?} at offset {}", value.tag_type, offset),
+                    format_args!("tag type {:?} of previous fragments of tag {:?}", v, tag),
+                ));
+            }
+            Some(_) => {}
+            None => tag_type = Some(value.tag_type),
+        }
+        buf.put_slice(&value.value[..]);
+        if !has_more {
+            break;
+        }
+        if value.value.is_empty() {
+            return Err(Error::invalid_length(
+                0,
+                format_args!("data at offset {} of tag {:?}", offset, tag),
+            ));
+        }
+    }
+    let tag_type = tag_type.ok_or_else(|| {
+        ClientError::invalid_value("no tag type", format_args!("tag type of tag {:?}", tag))
+    })?;
+    let mut decoder = LittleEndianDecoder::<ClientError>::new(buf.freeze());
+    let value: R = decoder.decode_any()?;
+    Ok(TagValue { tag_type, value })
+}
+
 /// Write Tag Fragmented Service, enables client applications to write to a tag
 /// in the controller whose data will not fit into a single packet (approximately 500 bytes)
 async fn ab_write_tag_fragmented<C, D>(
@@ -312,8 +621,78 @@ where
     Ok(resp.0.status.has_more())
 }
 
+/// Write Tag Fragmented Service, splits the encoded value into chunks of `max_size` at most
+async fn ab_write_tag_large<C, D>(
+    client: &mut C,
+    tag: EPath,
+    value: TagValue<D>,
+    max_size: usize,
+) -> Result<()>
+where
+    C: MessageService<Error = ClientError>,
+    TagValue<D>: Encode,
+{
+    // encoded as: tag type | number of elements | data
+    let mut buf = LittleEndianEncoder::<ClientError>::new().encode_to_bytes(value)?;
+    let mut decoder = LittleEndianDecoder::<ClientError>::new(buf.clone());
+    let tag_type: TagType = decoder.decode_any()?;
+    let count: u16 = decoder.decode_any()?;
+    buf.advance(tag_type.bytes_count() + 2);
+    if count == 0 || buf.len() % count as usize != 0 {
+        return Err(Error::invalid_length(
+            buf.len(),
+            format_args!(
+                "data of {} elements of the same size writing tag {:?}",
+                count, tag
+            ),
+        ));
+    }
+    // service | path size | path | tag type | number of elements | offset
+    let overhead = 2 + tag.bytes_count() + tag_type.bytes_count() + 2 + 4;
+    let chunk_size = fragment_size(
+        max_size.saturating_sub(overhead),
+        buf.len() / count as usize,
+    );
+    if chunk_size == 0 {
+        return Err(Error::invalid_value(
+            format_args!("message size {}", max_size),
+            format_args!("message size for at least one byte of tag {:?}", tag),
+        ));
+    }
+    let mut offset = 0;
+    while !buf.is_empty() {
+        if offset > u16::MAX as usize {
+            return Err(Error::invalid_length(
+                offset,
+                format_args!("data offset within u16 writing tag {:?}", tag),
+            ));
+        }
+        let data = buf.split_to(chunk_size.min(buf.len()));
+        let size = data.len();
+        let req = WriteFragmentedRequest::new(data)
+            .tag(tag.clone())
+            .tag_type(tag_type)
+            .count(count)
+            .offset(offset as u16);
+        ab_write_tag_fragmented(client, req).await?;
+        offset += size;
+    }
+    Ok(())
+}
+
+/// number of bytes for each fragment, rounded down to whole elements;
+/// rounded down to 4 bytes if one element does not fit
+#[inline]
+fn fragment_size(avail: usize, elem_size: usize) -> usize {
+    if elem_size > 0 && elem_size <= avail {
+        avail - avail % elem_size
+    } else {
+        avail & !0b11
+    }
+}
+
 /// Read Modify Write Tag Service, modifies Tag data with individual bit resolution
-async fn ab_read_modify_write<C, const N: usize>(
+pub(super) async fn ab_read_modify_write<C, const N: usize>(
     client: &mut C,
     req: ReadModifyWriteRequest<N>,
 ) -> Result<()>
@@ -402,6 +781,21 @@ impl<const N: usize> ReadModifyWriteRequest<N> {
     pub fn and_mask_mut(&mut self) -> &mut [u8] {
         &mut self.and_mask
     }
+
+    /// set or reset a single bit, other bits are kept untouched;
+    /// panics if `bit` out of range
+    pub fn bit(mut self, bit: usize, value: bool) -> Self {
+        assert!(bit < N * 8);
+        let mask = 1 << (bit % 8);
+        if value {
+            self.or_mask[bit / 8] |= mask;
+            self.and_mask[bit / 8] |= mask;
+        } else {
+            self.or_mask[bit / 8] &= !mask;
+            self.and_mask[bit / 8] &= !mask;
+        }
+        self
+    }
 }
 
 impl<const N: usize> Default for ReadModifyWriteRequest<N> {
@@ -498,21 +892,352 @@ impl Default for ReadFragmentedRequest {
 pub struct TagRequest {
     tag: EPath,
     count: u16,
+    size_hint: Option<usize>,
+}
+
+impl TagRequest {
+    /// default number of bytes of a single element, used to estimate reply size
+    pub const DEFAULT_ELEMENT_SIZE: usize = 8;
+
+    pub fn new(tag: EPath) -> Self {
+        Self {
+            tag,
+            count: 1,
+            size_hint: None,
+        }
+    }
+
+    /// number of elements, default 1
+    pub fn count(mut self, val: u16) -> Self {
+        self.count = val;
+        self
+    }
+
+    /// expected number of data bytes in the reply, used to pack multiple requests;
+    /// default `count` * [`TagRequest::DEFAULT_ELEMENT_SIZE`]
+    pub fn size_hint(mut self, val: usize) -> Self {
+        self.size_hint = Some(val);
+        self
+    }
+
+    /// estimated number of bytes of the read tag request
+    #[inline]
+    fn request_size(&self) -> usize {
+        // service | path size | path | number of elements
+        2 + self.tag.bytes_count() + 2
+    }
+
+    /// estimated number of bytes of the read tag reply
+    #[inline]
+    fn reply_size(&self) -> usize {
+        let data_size = self
+            .size_hint
+            .unwrap_or(self.count as usize * Self::DEFAULT_ELEMENT_SIZE);
+        // service | reserved | status | extended status size | tag type | data
+        4 + 4 + data_size
+    }
 }
 
 impl From<EPath> for TagRequest {
     #[inline]
     fn from(tag: EPath) -> Self {
-        Self { tag, count: 1 }
+        Self::new(tag)
     }
 }
 
 impl From<(EPath, u16)> for TagRequest {
     #[inline]
     fn from(src: (EPath, u16)) -> Self {
-        Self {
-            tag: src.0,
-            count: src.1,
+        Self::new(src.0).count(src.1)
+    }
+}
+
+/// request for [`AbService::write_tags`], holds the encoded tag value
+#[derive(Debug, Clone)]
+pub struct WriteTagRequest {
+    tag: EPath,
+    data: Bytes,
+}
+
+impl WriteTagRequest {
+    /// encode the tag value for later write
+    pub fn new<D>(tag: EPath, value: TagValue<D>) -> Result<Self>
+    where
+        TagValue<D>: Encode,
+    {
+        let data = LittleEndianEncoder::<ClientError>::new().encode_to_bytes(value)?;
+        Ok(Self { tag, data })
+    }
+
+    /// tag path
+    #[inline]
+    pub fn tag(&self) -> &EPath {
+        &self.tag
+    }
+
+    /// estimated number of bytes of the write tag request
+    #[inline]
+    fn request_size(&self) -> usize {
+        // service | path size | path | tag type | number of elements | data
+        2 + self.tag.bytes_count() + self.data.len()
+    }
+
+    /// estimated number of bytes of the write tag reply
+    #[inline]
+    fn reply_size(&self) -> usize {
+        // service | reserved | status | extended status size
+        4
+    }
+}
+
+/// split items into batches, so that both the multiple service request and reply
+/// of each batch fit into `max_size`; an item too large is put into a batch alone
+fn pack_batches(items: impl Iterator<Item = (usize, usize)>, max_size: usize) -> Vec<Range<usize>> {
+    // service | path size | path | number of services
+    const REQUEST_OVERHEAD: usize = 2 + 4 + 2;
+    // service | reserved | status | extended status size | number of services
+    const REPLY_OVERHEAD: usize = 4 + 2;
+    let mut res = Vec::new();
+    let mut start = 0;
+    let mut request_size = REQUEST_OVERHEAD;
+    let mut reply_size = REPLY_OVERHEAD;
+    let mut end = 0;
+    for (i, (req, reply)) in items.enumerate() {
+        // 2 bytes offset for each service
+        let req = req + 2;
+        let reply = reply + 2;
+        if i > start && (request_size + req > max_size || reply_size + reply > max_size) {
+            res.push(start..i);
+            start = i;
+            request_size = REQUEST_OVERHEAD;
+            reply_size = REPLY_OVERHEAD;
+        }
+        request_size += req;
+        reply_size += reply;
+        end = i + 1;
+    }
+    if end > start {
+        res.push(start..end);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cip::service::MessageService;
+    use std::collections::VecDeque;
+
+    /// replies multiple service packets of write tag requests with scripted statuses
+    #[derive(Default)]
+    struct MultipleWriteService {
+        /// general status of each reply, success if missing
+        statuses: Vec<u8>,
+        /// number of requests of each multiple service packet
+        batches: Vec<usize>,
+        /// reply one less than requested
+        drop_last: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl MessageService for MultipleWriteService {
+        type Error = ClientError;
+
+        async fn send<'de, P, D, R>(&mut self, mr: MessageRequest<P, D>) -> Result<R>
+        where
+            P: Encode + Send + Sync,
+            D: Encode + Send + Sync,
+            R: MessageReplyInterface + Decode<'de> + 'static,
+        {
+            let mut data = LittleEndianEncoder::<ClientError>::new().encode_to_bytes(mr.data)?;
+            let count = data.get_u16_le() as usize;
+            let start = self.batches.iter().sum::<usize>();
+            self.batches.push(count);
+            let count = if self.drop_last { count - 1 } else { count };
+
+            let mut buf = BytesMut::new();
+            buf.put_slice(&[mr.service_code | REPLY_MASK, 0, 0, 0]);
+            buf.put_u16_le(count as u16);
+            for i in 0..count {
+                buf.put_u16_le((2 + 2 * count + 4 * i) as u16);
+            }
+            for i in start..start + count {
+                let status = self.statuses.get(i).copied().unwrap_or_default();
+                buf.put_slice(&[SERVICE_WRITE_TAG | REPLY_MASK, 0, status, 0]);
+            }
+            LittleEndianDecoder::<ClientError>::new(buf.freeze()).decode_any()
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn closed(&self) -> bool {
+            false
         }
     }
+
+    /// replies Read Tag Fragmented requests with scripted fragments: status, type code and data
+    struct FragmentedReadService(VecDeque<(u8, u16, &'static [u8])>);
+
+    #[async_trait::async_trait]
+    impl MessageService for FragmentedReadService {
+        type Error = ClientError;
+
+        async fn send<'de, P, D, R>(&mut self, mr: MessageRequest<P, D>) -> Result<R>
+        where
+            P: Encode + Send + Sync,
+            D: Encode + Send + Sync,
+            R: MessageReplyInterface + Decode<'de> + 'static,
+        {
+            let (status, type_code, data) = self.0.pop_front().expect("expected fragment");
+            let mut buf = BytesMut::new();
+            buf.put_slice(&[mr.service_code | REPLY_MASK, 0, status, 0]);
+            buf.put_u16_le(type_code);
+            buf.put_slice(data);
+            LittleEndianDecoder::<ClientError>::new(buf.freeze()).decode_any()
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn closed(&self) -> bool {
+            false
+        }
+    }
+
+    fn write_requests(n: usize) -> Vec<WriteTagRequest> {
+        (0..n)
+            .map(|i| {
+                let value = TagValue {
+                    tag_type: TagType::Dint,
+                    value: i as i32,
+                };
+                WriteTagRequest::new(EPath::from_symbol("a"), value).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fragment_size() {
+        // whole elements
+        assert_eq!(fragment_size(470, 4), 468);
+        assert_eq!(fragment_size(470, 86), 430);
+        assert_eq!(fragment_size(470, 470), 470);
+        // element larger than a fragment
+        assert_eq!(fragment_size(470, 1408), 468);
+        assert_eq!(fragment_size(0, 4), 0);
+    }
+
+    #[test]
+    fn test_pack_batches() {
+        let res = pack_batches(core::iter::empty(), 100);
+        assert!(res.is_empty());
+
+        // request: 8 + 3 * 12 = 44; reply: 6 + 3 * 18 = 60
+        let res = pack_batches([(10, 16); 3].into_iter(), 60);
+        assert_eq!(res, vec![0..3]);
+
+        let res = pack_batches([(10, 16); 5].into_iter(), 60);
+        assert_eq!(res, vec![0..3, 3..5]);
+
+        // too large
+        let res = pack_batches([(10, 16), (10, 100), (10, 16)].into_iter(), 60);
+        assert_eq!(res, vec![0..1, 1..2, 2..3]);
+    }
+
+    #[test]
+    fn test_write_tag_request() {
+        let req = &write_requests(1)[0];
+        // tag type | number of elements | value
+        assert_eq!(req.data.as_ref(), &[0xC4, 0, 1, 0, 0, 0, 0, 0]);
+        // service | path size | path | data
+        assert_eq!(req.request_size(), 2 + 4 + 8);
+        assert_eq!(req.reply_size(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_write_tags_batches() {
+        let mut service = MultipleWriteService {
+            statuses: vec![0, 0, 0, 0x05],
+            ..Default::default()
+        };
+        // request: 8 + 2 * 16 = 40
+        let res = ab_write_tags(&mut service, &write_requests(5), 40)
+            .await
+            .unwrap();
+        assert_eq!(service.batches, vec![2, 2, 1]);
+        assert_eq!(res.len(), 5);
+        assert!(res[..3].iter().all(|v| v.is_ok()));
+        // per-item error status does not fail other items
+        assert_eq!(res[3].general, 0x05);
+        assert!(res[4].is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_write_tags_missing_reply() {
+        let mut service = MultipleWriteService {
+            drop_last: true,
+            ..Default::default()
+        };
+        let res = ab_write_tags(&mut service, &write_requests(2), 100).await;
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_reply_iter_next_unchecked() {
+        let mut service = MultipleWriteService {
+            statuses: vec![0x05, 0],
+            ..Default::default()
+        };
+        let requests = write_requests(2);
+        let mut iter = futures::executor::block_on(
+            service
+                .multiple_service()
+                .push_all(
+                    requests
+                        .iter()
+                        .map(|v| MessageRequest::new(SERVICE_WRITE_TAG, &v.tag, v.data.clone())),
+                )
+                .call(),
+        )
+        .unwrap();
+        let reply = iter.next_unchecked::<()>().unwrap().unwrap();
+        assert_eq!(reply.status.general, 0x05);
+        let reply = iter.next_unchecked::<()>().unwrap().unwrap();
+        assert!(reply.status.is_ok());
+        assert!(iter.next_unchecked::<()>().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_tag_large() {
+        let fragments = [
+            (0x06, 0xC4, &[1, 0, 0, 0][..]),
+            (0x00, 0xC4, &[2, 0, 0, 0][..]),
+        ];
+        let mut service = FragmentedReadService(fragments.into_iter().collect());
+        let res: TagValue<(i32, i32)> = ab_read_tag_large(&mut service, EPath::from_symbol("a"))
+            .await
+            .unwrap();
+        assert_eq!(res.tag_type, TagType::Dint);
+        assert_eq!(res.value, (1, 2));
+
+        // no data received
+        let fragments = [(0x06, 0xC4, &[][..])];
+        let mut service = FragmentedReadService(fragments.into_iter().collect());
+        let res = ab_read_tag_large::<_, _, i32>(&mut service, EPath::from_symbol("a")).await;
+        assert_eq!(res.unwrap_err().kind(), crate::ErrorKind::Decode);
+
+        // tag type changed
+        let fragments = [
+            (0x06, 0xC4, &[1, 0, 0, 0][..]),
+            (0x00, 0xC3, &[2, 0][..]),
+        ];
+        let mut service = FragmentedReadService(fragments.into_iter().collect());
+        let res = ab_read_tag_large::<_, _, i32>(&mut service, EPath::from_symbol("a")).await;
+        let err = res.unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Decode);
+        assert!(err.to_string().contains("at offset 4"));
+    }
 }
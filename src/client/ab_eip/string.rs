@@ -0,0 +1,219 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::{TagType, TagValue};
+use bytes::{Buf, BufMut, BytesMut};
+use core::{fmt, ops::Deref};
+use rseip_core::codec::*;
+
+/// structure handle of built-in STRING
+pub const STRING_STRUCTURE_HANDLE: u16 = 0x0FCE;
+
+/// capacity of built-in STRING
+pub const STRING_CAPACITY: usize = 82;
+
+/// built-in data type: STRING, 82 characters at most
+#[allow(clippy::upper_case_acronyms)]
+pub type STRING = AbString<STRING_CAPACITY>;
+
+/// Logix string structure, `LEN` of DINT followed by `DATA` of `SINT[N]`;
+/// use `N` of the `DATA` array size for user-defined string types, eg. `AbString<20>` for `STRING_20`.
+///
+/// the value is truncated to `N` bytes if too long
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AbString<const N: usize = STRING_CAPACITY> {
+    data: String,
+}
+
+impl<const N: usize> AbString<N> {
+    /// number of bytes of the structure, `DATA` padded to 4 bytes
+    pub const SIZE: usize = (4 + N + 3) & !3;
+
+    /// the value is truncated to `N` bytes if too long
+    #[inline]
+    pub fn new(data: impl Into<String>) -> Self {
+        let mut data = data.into();
+        let len = truncated_len(&data, N);
+        data.truncate(len);
+        Self { data }
+    }
+
+    /// max number of bytes
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.data
+    }
+
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.data
+    }
+}
+
+impl<const N: usize> Deref for AbString<N> {
+    type Target = str;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<const N: usize> fmt::Display for AbString<N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.data, f)
+    }
+}
+
+impl<const N: usize> From<&str> for AbString<N> {
+    #[inline]
+    fn from(src: &str) -> Self {
+        Self::new(src)
+    }
+}
+
+impl<const N: usize> From<String> for AbString<N> {
+    #[inline]
+    fn from(src: String) -> Self {
+        Self::new(src)
+    }
+}
+
+impl<const N: usize> From<AbString<N>> for String {
+    #[inline]
+    fn from(src: AbString<N>) -> Self {
+        src.data
+    }
+}
+
+impl<const N: usize> Encode for AbString<N> {
+    #[inline]
+    fn encode_by_ref<A: Encoder>(
+        &self,
+        buf: &mut BytesMut,
+        encoder: &mut A,
+    ) -> Result<(), A::Error> {
+        let data = self.data.as_bytes();
+        // LEN
+        encoder.encode_i32(data.len() as i32, buf)?;
+        // DATA, padded
+        buf.put_slice(data);
+        buf.put_bytes(0, Self::SIZE - 4 - data.len());
+        Ok(())
+    }
+
+    #[inline]
+    fn bytes_count(&self) -> usize {
+        Self::SIZE
+    }
+}
+
+impl<'de, const N: usize> Decode<'de> for AbString<N> {
+    #[inline]
+    fn decode<D>(mut decoder: D) -> Result<Self, D::Error>
+    where
+        D: Decoder<'de>,
+    {
+        decoder.ensure_size(Self::SIZE)?;
+        let len = decoder.decode_i32().clamp(0, N as i32) as usize;
+        let data = decoder.buf_mut().copy_to_bytes(Self::SIZE - 4);
+        Ok(Self {
+            data: String::from_utf8_lossy(&data[..len]).into_owned(),
+        })
+    }
+}
+
+impl<const N: usize> Encode for TagValue<AbString<N>> {
+    #[inline]
+    fn encode_by_ref<A: Encoder>(
+        &self,
+        buf: &mut BytesMut,
+        encoder: &mut A,
+    ) -> Result<(), A::Error> {
+        self.tag_type.encode_by_ref(buf, encoder)?;
+        buf.put_slice(&[1, 0]);
+        self.value.encode_by_ref(buf, encoder)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn bytes_count(&self) -> usize {
+        self.tag_type.bytes_count() + 2 + AbString::<N>::SIZE
+    }
+}
+
+impl<'de, const N: usize> Decode<'de> for TagValue<AbString<N>> {
+    #[inline]
+    fn decode<D>(mut decoder: D) -> Result<Self, D::Error>
+    where
+        D: Decoder<'de>,
+    {
+        let tag_type = decoder.decode_any()?;
+        let value = decoder.decode_any()?;
+        Ok(Self { tag_type, value })
+    }
+}
+
+impl TagValue<STRING> {
+    /// built-in STRING value
+    #[inline]
+    pub fn string(value: impl Into<STRING>) -> Self {
+        Self {
+            tag_type: TagType::STRING,
+            value: value.into(),
+        }
+    }
+}
+
+/// number of bytes of `s` that fit in `max`, not splitting characters
+#[inline]
+pub(crate) fn truncated_len(s: &str, max: usize) -> usize {
+    if s.len() <= max {
+        return s.len();
+    }
+    let mut len = max;
+    while !s.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientError;
+
+    #[test]
+    fn test_string_roundtrip() {
+        assert_eq!(STRING::SIZE, 88);
+        assert_eq!(AbString::<20>::SIZE, 24);
+
+        let value = AbString::<4>::new("abcdef");
+        assert_eq!(value.as_str(), "abcd");
+        let buf = LittleEndianEncoder::<ClientError>::new()
+            .encode_to_bytes(&value)
+            .unwrap();
+        assert_eq!(&buf[..], &[4, 0, 0, 0, b'a', b'b', b'c', b'd']);
+
+        let value = AbString::<6>::new("ab");
+        let buf = LittleEndianEncoder::<ClientError>::new()
+            .encode_to_bytes(&value)
+            .unwrap();
+        assert_eq!(&buf[..], &[2, 0, 0, 0, b'a', b'b', 0, 0, 0, 0, 0, 0]);
+        let res: AbString<6> = LittleEndianDecoder::<ClientError>::new(buf)
+            .decode_any()
+            .unwrap();
+        assert_eq!(res, value);
+
+        // not splitting characters
+        assert_eq!(AbString::<2>::new("aé").as_str(), "a");
+    }
+}
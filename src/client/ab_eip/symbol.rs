@@ -4,20 +4,78 @@
 // Copyright: 2021, Joylei <leingliu@gmail.com>
 // License: MIT
 
-use super::{interceptor::HasMoreInterceptor, HasMore, CLASS_SYMBOL, REPLY_MASK};
+use super::{interceptor::HasMoreInterceptor, HasMore, TagType, CLASS_SYMBOL, REPLY_MASK};
 use crate::{
     cip::{epath::EPath, service::MessageService, MessageRequest},
     ClientError, Result,
 };
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use core::{convert::TryFrom, fmt, slice, str};
 use futures_util::{stream, Stream};
 use rseip_cip::MessageReplyInterface;
 use rseip_core::{codec::BytesHolder, hex::AsHex, Error};
+use smallvec::{smallvec, SmallVec};
 use std::borrow::Cow;
 
+/// name prefix of program symbols
+pub(crate) const PROGRAM_PREFIX: &str = "Program:";
+
+/// symbol instance attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolAttribute {
+    /// attribute 1, symbol name
+    Name,
+    /// attribute 2, symbol type
+    SymbolType,
+    /// attribute 7, number of bytes of one element
+    ByteSize,
+    /// attribute 8, array dimensions
+    Dimensions,
+    /// attribute 9, constant flag; not supported by older controllers
+    Constant,
+    /// attribute 10, external access; not supported by older controllers
+    ExternalAccess,
+}
+
+impl SymbolAttribute {
+    /// attribute id
+    #[inline]
+    pub fn id(&self) -> u16 {
+        match self {
+            Self::Name => 1,
+            Self::SymbolType => 2,
+            Self::ByteSize => 7,
+            Self::Dimensions => 8,
+            Self::Constant => 9,
+            Self::ExternalAccess => 10,
+        }
+    }
+}
+
+/// external access of tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExternalAccess {
+    ReadWrite,
+    ReadOnly,
+    None,
+    /// reserved values
+    Unknown(u8),
+}
+
+impl From<u8> for ExternalAccess {
+    #[inline]
+    fn from(src: u8) -> Self {
+        match src {
+            0 => Self::ReadWrite,
+            2 => Self::ReadOnly,
+            3 => Self::None,
+            v => Self::Unknown(v),
+        }
+    }
+}
+
 /// symbol instance
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Hash, PartialEq, Eq, Default)]
 pub struct SymbolInstance<'a> {
     /// instance id
     pub id: u16,
@@ -25,15 +83,27 @@ pub struct SymbolInstance<'a> {
     pub name: Cow<'a, str>,
     /// symbol data type
     pub symbol_type: SymbolType,
+    /// number of bytes of one element, if retrieved
+    pub byte_size: Option<u16>,
+    /// array dimensions, if retrieved; unused dimensions are 0
+    pub dims: Option<[u32; 3]>,
+    /// external access, if retrieved
+    pub external_access: Option<ExternalAccess>,
+    /// constant flag, if retrieved
+    pub constant: Option<bool>,
 }
 
 impl fmt::Debug for SymbolInstance<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("SymbolInstance")
-            .field("id", &self.id.as_hex())
+        let mut d = f.debug_struct("SymbolInstance");
+        d.field("id", &self.id.as_hex())
             .field("name", &self.name)
-            .field("symbol_type", &self.symbol_type)
-            .finish()
+            .field("symbol_type", &self.symbol_type);
+        self.byte_size.map(|v| d.field("byte_size", &v));
+        self.dims.map(|v| d.field("dims", &v));
+        self.external_access.map(|v| d.field("external_access", &v));
+        self.constant.map(|v| d.field("constant", &v));
+        d.finish()
     }
 }
 
@@ -43,6 +113,41 @@ impl SymbolInstance<'_> {
     pub fn is_module_defined(&self) -> bool {
         self.name.contains(':')
     }
+
+    /// program symbol, eg. `Program:MainProgram`
+    #[inline]
+    pub fn is_program(&self) -> bool {
+        self.name.starts_with(PROGRAM_PREFIX)
+    }
+
+    /// system symbol, name starts with `__` or system flag set in symbol type
+    #[inline]
+    pub fn is_system(&self) -> bool {
+        self.name.starts_with("__") || self.symbol_type.is_system()
+    }
+
+    /// constant tag, not writable; false if the constant flag not retrieved
+    #[inline]
+    pub fn is_constant(&self) -> bool {
+        self.constant.unwrap_or_default()
+    }
+
+    /// number of elements if array dimensions retrieved; 1 if not an array
+    #[inline]
+    pub fn element_count(&self) -> Option<u32> {
+        let dims = self.dims?;
+        let rank = self.symbol_type.dims() as usize;
+        Some(dims[..rank].iter().product())
+    }
+
+    /// copy borrowed name
+    #[inline]
+    pub fn into_owned(self) -> SymbolInstance<'static> {
+        SymbolInstance {
+            name: Cow::Owned(self.name.into_owned()),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -118,6 +223,13 @@ impl SymbolType {
         !self.is_struct()
     }
 
+    /// system symbol, bit 12 set
+    #[inline]
+    pub fn is_system(&self) -> bool {
+        const MASK: u16 = 1 << 12;
+        self.0 & MASK == MASK
+    }
+
     /// system predefined struct
     #[inline]
     pub fn is_predefined(&self) -> bool {
@@ -134,6 +246,13 @@ impl SymbolType {
         }
     }
 
+    /// tag type if atomic
+    #[inline]
+    pub fn tag_type(&self) -> Option<TagType> {
+        self.type_code()
+            .and_then(|v| TagType::from_type_code(v as u16))
+    }
+
     /// dims: 0, 1, 2, 3
     #[inline]
     pub fn dims(&self) -> u8 {
@@ -200,6 +319,8 @@ impl From<SymbolType> for u16 {
 /// Any symbol instances that represents tags whose External Access is set to None are not included in the reply data.
 pub struct GetInstanceAttributeList<'a, T> {
     inner: &'a mut T,
+    program: Option<String>,
+    attributes: SmallVec<[SymbolAttribute; 6]>,
     start_instance: u16,
     all: bool,
 }
@@ -208,11 +329,30 @@ impl<'a, T> GetInstanceAttributeList<'a, T> {
     pub(crate) fn new(inner: &'a mut T) -> Self {
         Self {
             inner,
+            program: None,
+            attributes: smallvec![SymbolAttribute::Name, SymbolAttribute::SymbolType],
             start_instance: 0,
             all: true,
         }
     }
 
+    /// list symbols under the program scope, eg. `Program:MainProgram`;
+    /// controller scope by default
+    pub fn program(mut self, program: impl Into<String>) -> Self {
+        self.program = Some(program.into());
+        self
+    }
+
+    /// attributes to retrieve; name and symbol type are always retrieved
+    pub fn attributes(mut self, attributes: &[SymbolAttribute]) -> Self {
+        for attr in attributes {
+            if !self.attributes.contains(attr) {
+                self.attributes.push(*attr);
+            }
+        }
+        self
+    }
+
     /// with starting instance id
     pub fn start_instance(mut self, instance_id: u16) -> Self {
         self.start_instance = instance_id;
@@ -231,70 +371,83 @@ impl<'a, T> GetInstanceAttributeList<'a, T> {
 impl<'a, T: MessageService<Error = ClientError>> GetInstanceAttributeList<'a, T> {
     pub fn call(self) -> impl Stream<Item = Result<SymbolInstance<'a>>> {
         let all = self.all;
+        let program = self.program;
+        let attributes = self.attributes;
         stream::try_unfold(
             State::Request {
                 ctx: self.inner,
                 start_instance: self.start_instance,
             },
-            move |mut state| async move {
-                loop {
-                    match state {
-                        State::Request {
-                            ctx,
-                            start_instance,
-                        } => {
-                            match get_attribute_list(ctx, start_instance).await {
-                                Ok((has_more, data)) => {
-                                    state = State::HasData {
-                                        ctx,
-                                        start_instance,
-                                        has_more,
-                                        data,
-                                    }
-                                }
-                                Err(e) => {
-                                    //state = State::End;
-                                    return Err(e);
-                                }
-                            }
-                        }
-                        State::HasData {
-                            ctx,
-                            start_instance,
-                            has_more,
-                            mut data,
-                        } => {
-                            if !data.is_empty() {
-                                match SymbolInstance::try_from(&mut data) {
-                                    Ok(item) => {
-                                        let start_instance = item.id; // update start instance
-                                        return Ok(Some((
-                                            item,
-                                            State::HasData {
-                                                ctx,
-                                                start_instance,
-                                                has_more,
-                                                data,
-                                            },
-                                        )));
+            move |mut state| {
+                let program = program.clone();
+                let attributes = attributes.clone();
+                async move {
+                    loop {
+                        match state {
+                            State::Request {
+                                ctx,
+                                start_instance,
+                            } => {
+                                match get_attribute_list(
+                                    ctx,
+                                    program.as_deref(),
+                                    &attributes,
+                                    start_instance,
+                                )
+                                .await
+                                {
+                                    Ok((has_more, data)) => {
+                                        state = State::HasData {
+                                            ctx,
+                                            start_instance,
+                                            has_more,
+                                            data,
+                                        }
                                     }
                                     Err(e) => {
-                                        //dbg!(&e);
                                         //state = State::End;
                                         return Err(e);
                                     }
                                 }
-                            } else if has_more && all {
-                                //dbg!(has_more, "new request");
-                                state = State::Request {
-                                    ctx,
-                                    start_instance: start_instance + 1,
-                                };
-                            } else {
-                                state = State::End;
                             }
+                            State::HasData {
+                                ctx,
+                                start_instance,
+                                has_more,
+                                mut data,
+                            } => {
+                                if !data.is_empty() {
+                                    match decode_instance(&mut data, &attributes) {
+                                        Ok(item) => {
+                                            let start_instance = item.id; // update start instance
+                                            return Ok(Some((
+                                                item,
+                                                State::HasData {
+                                                    ctx,
+                                                    start_instance,
+                                                    has_more,
+                                                    data,
+                                                },
+                                            )));
+                                        }
+                                        Err(e) => {
+                                            //dbg!(&e);
+                                            //state = State::End;
+                                            return Err(e);
+                                        }
+                                    }
+                                } else if has_more && all {
+                                    //dbg!(has_more, "new request");
+                                    state = State::Request {
+                                        ctx,
+                                        start_instance: start_instance + 1,
+                                    };
+                                } else {
+                                    state = State::End;
+                                }
+                            }
+                            State::End => return Ok(None),
                         }
-                        State::End => return Ok(None),
                     }
                 }
             },
@@ -318,17 +471,23 @@ enum State<'a, T> {
 
 async fn get_attribute_list<T: MessageService<Error = ClientError>>(
     ctx: &mut T,
+    program: Option<&str>,
+    attributes: &[SymbolAttribute],
     start_instance: u16,
 ) -> Result<(bool, Bytes)> {
     const SERVICE_GET_INSTANCE_ATTRIBUTE_LIST: u8 = 0x55;
-    let path = EPath::default()
-        .with_class(CLASS_SYMBOL)
-        .with_instance(start_instance);
-    let data: &[u8] = &[
-        0x02, 0x00, // number of attributes
-        0x01, 0x00, // attribute 1 - symbol name
-        0x02, 0x00, // attribute 2 - symbol type
-    ];
+    let path = match program {
+        Some(program) => EPath::from_symbol(program),
+        None => EPath::default(),
+    };
+    let path = path.with_class(CLASS_SYMBOL).with_instance(start_instance);
+    // number of attributes | attribute ids
+    let mut data = BytesMut::with_capacity(2 + 2 * attributes.len());
+    data.put_u16_le(attributes.len() as u16);
+    for attr in attributes {
+        data.put_u16_le(attr.id());
+    }
+    let data = data.freeze();
     let resp: HasMoreInterceptor<BytesHolder> = ctx
         .send(MessageRequest::new(
             SERVICE_GET_INSTANCE_ATTRIBUTE_LIST,
@@ -343,31 +502,72 @@ async fn get_attribute_list<T: MessageService<Error = ClientError>>(
 impl TryFrom<&mut Bytes> for SymbolInstance<'_> {
     type Error = ClientError;
 
+    #[inline]
     fn try_from(buf: &mut Bytes) -> Result<Self> {
-        if buf.remaining() < 6 {
-            return Err(Error::invalid_length(buf.len(), 6));
-        }
-        let id = buf.get_u16_le();
-        buf.advance(2);
-        let name_len = buf.get_u8() as usize;
-        buf.advance(1);
-        if buf.remaining() < name_len + 2 {
-            return Err(Error::invalid_length(buf.remaining(), name_len + 2));
+        decode_instance(buf, &[SymbolAttribute::Name, SymbolAttribute::SymbolType])
+    }
+}
+
+/// instance id, then attribute values in the requested order
+fn decode_instance<'a>(
+    buf: &mut Bytes,
+    attributes: &[SymbolAttribute],
+) -> Result<SymbolInstance<'a>> {
+    ensure_size(buf, 4)?;
+    let mut res = SymbolInstance {
+        id: buf.get_u16_le(),
+        ..Default::default()
+    };
+    buf.advance(2);
+    for attr in attributes {
+        match attr {
+            SymbolAttribute::Name => {
+                ensure_size(buf, 2)?;
+                let name_len = buf.get_u16_le() as usize;
+                ensure_size(buf, name_len)?;
+                res.name = unsafe {
+                    let name_buf = buf.split_to(name_len);
+                    let buf = name_buf.as_ptr();
+                    let buf = slice::from_raw_parts(buf, name_len);
+                    let name = str::from_utf8_unchecked(buf);
+                    Cow::from(name)
+                };
+            }
+            SymbolAttribute::SymbolType => {
+                ensure_size(buf, 2)?;
+                res.symbol_type = SymbolType(buf.get_u16_le());
+            }
+            SymbolAttribute::ByteSize => {
+                ensure_size(buf, 2)?;
+                res.byte_size = Some(buf.get_u16_le());
+            }
+            SymbolAttribute::Dimensions => {
+                ensure_size(buf, 12)?;
+                res.dims = Some([
+                    buf.get_u32_le(),
+                    buf.get_u32_le(),
+                    buf.get_u32_le(),
+                ]);
+            }
+            SymbolAttribute::Constant => {
+                ensure_size(buf, 1)?;
+                res.constant = Some(buf.get_u8() != 0);
+            }
+            SymbolAttribute::ExternalAccess => {
+                ensure_size(buf, 1)?;
+                res.external_access = Some(buf.get_u8().into());
+            }
         }
-        let name = unsafe {
-            let name_buf = buf.split_to(name_len);
-            let buf = name_buf.as_ptr();
-            let buf = slice::from_raw_parts(buf, name_len as usize);
-            let name = str::from_utf8_unchecked(buf);
-            Cow::from(name)
-        };
-        let symbol_type = buf.get_u16_le();
-        Ok(SymbolInstance {
-            id,
-            name,
-            symbol_type: SymbolType(symbol_type),
-        })
     }
+    Ok(res)
+}
+
+#[inline]
+fn ensure_size(buf: &Bytes, size: usize) -> Result<()> {
+    if buf.remaining() < size {
+        return Err(Error::invalid_length(buf.remaining(), size));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -379,5 +579,50 @@ mod tests {
         let sym_type = SymbolType(0x82E9);
         assert!(sym_type.is_struct());
         assert!(!sym_type.is_atomic());
+        assert!(!sym_type.is_system());
+        assert!(SymbolType(0x10C4).is_system());
+        assert_eq!(SymbolType(0x20CB).tag_type(), Some(TagType::Lreal));
+        assert_eq!(sym_type.tag_type(), None);
+    }
+
+    #[test]
+    fn test_decode_instance() {
+        let mut buf = Bytes::from_static(&[
+            0x10, 0x00, 0x00, 0x00, // instance id
+            0x03, 0x00, b'a', b'b', b'c', // name
+            0xC4, 0x40, // symbol type, 2 dims
+            0x04, 0x00, // byte size
+            0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // dims
+            0x01, // constant
+            0x02, // external access
+        ]);
+        let attrs = [
+            SymbolAttribute::Name,
+            SymbolAttribute::SymbolType,
+            SymbolAttribute::ByteSize,
+            SymbolAttribute::Dimensions,
+            SymbolAttribute::Constant,
+            SymbolAttribute::ExternalAccess,
+        ];
+        let res = decode_instance(&mut buf, &attrs).unwrap();
+        assert_eq!(res.id, 0x10);
+        assert_eq!(res.name, "abc");
+        assert_eq!(res.symbol_type.dims(), 2);
+        assert_eq!(res.byte_size, Some(4));
+        assert_eq!(res.element_count(), Some(6));
+        assert_eq!(res.external_access, Some(ExternalAccess::ReadOnly));
+        assert_eq!(res.constant, Some(true));
+        assert!(res.is_constant());
+        assert!(buf.is_empty());
+
+        // constant flag not retrieved
+        let mut buf = Bytes::from_static(&[
+            0x11, 0x00, 0x00, 0x00, // instance id
+            0x01, 0x00, b'x', // name
+            0xC1, 0x00, // symbol type
+        ]);
+        let res = SymbolInstance::try_from(&mut buf).unwrap();
+        assert_eq!(res.constant, None);
+        assert!(!res.is_constant());
     }
 }
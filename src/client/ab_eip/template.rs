@@ -17,17 +17,19 @@ use crate::{
     ClientError,
 };
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use core::{
-    ops::{Deref, DerefMut},
-    str,
-};
+use core::str;
 use rseip_cip::MessageReplyInterface;
 use rseip_core::{
     codec::{BytesHolder, Decode, Decoder},
     Error,
 };
-use smallvec::SmallVec;
-use std::collections::HashMap;
+
+mod cache;
+
+pub use cache::{CacheKey, CachedTemplate, TemplateCache};
+
+/// name prefix of host members for BOOL members
+const HOST_MEMBER_PREFIX: &str = "ZZZZZZZZZZ";
 
 /// template definition header size
 const HEADER_SIZE: u32 = 23;
@@ -63,8 +65,10 @@ impl<T: MessageService<Error = ClientError>> AbTemplateService for T {
         TemplateRead {
             inner: self,
             instance_id: template.instance_id,
+            handle: template.handle,
             object_size: template.object_size,
             member_count: template.member_count,
+            struct_size: template.struct_size,
             buf: Default::default(),
         }
     }
@@ -73,8 +77,10 @@ impl<T: MessageService<Error = ClientError>> AbTemplateService for T {
 pub struct TemplateRead<'a, T> {
     inner: &'a mut T,
     instance_id: u16,
+    handle: u16,
     object_size: u32,
     member_count: u16,
+    struct_size: u32,
     buf: BytesMut,
 }
 
@@ -83,8 +89,10 @@ impl<'a, T> TemplateRead<'a, T> {
         Self {
             inner,
             instance_id: 0,
+            handle: 0,
             object_size: 0,
             member_count: 0,
+            struct_size: 0,
             buf: Default::default(),
         }
     }
@@ -97,6 +105,18 @@ impl<'a, T> TemplateRead<'a, T> {
         self
     }
 
+    /// structure handle
+    pub fn handle(mut self, handle: u16) -> Self {
+        self.handle = handle;
+        self
+    }
+
+    /// template structure size
+    pub fn struct_size(mut self, struct_size: u32) -> Self {
+        self.struct_size = struct_size;
+        self
+    }
+
     /// template object definition size
     pub fn object_size(mut self, object_size: u32) -> Self {
         self.object_size = object_size;
@@ -132,7 +152,7 @@ impl<'a, T> TemplateRead<'a, T>
 where
     T: MessageService<Error = ClientError>,
 {
-    pub async fn call<'de>(&'de mut self) -> Result<TemplateDefinition<'de>, ClientError> {
+    pub async fn call(&mut self) -> Result<TemplateDefinition, ClientError> {
         if self.member_count == 0 {
             return Err(Error::custom(
                 "read template - need to initialize `member_count`",
@@ -151,19 +171,16 @@ where
                 (buf_len, total_bytes - buf_len)
             };
             //dbg!(total_bytes, offset);
-            let (has_more, data) = read_template(
-                self.inner,
-                self.instance_id,
-                offset as u32,
-                remaining as u16,
-            )
-            .await?;
+            let (has_more, data) =
+                read_template(self.inner, self.instance_id, offset, remaining as u16).await?;
             debug_assert!(!data.is_empty() && data.len() as u32 <= remaining);
             self.buf.put_slice(&data[..]);
             //dbg!(data.len(), self.buf.len());
             if !has_more {
                 // extract object
-                let res = decode_definition(&mut self.buf, self.member_count)?;
+                let mut res = decode_definition(&mut self.buf, self.member_count)?;
+                res.handle = self.handle;
+                res.struct_size = self.struct_size;
                 return Ok(res);
             }
             buf_len = self.buf.len() as u32;
@@ -192,38 +209,80 @@ where
 }
 
 /// template definition
-#[derive(Debug, Default)]
-pub struct TemplateDefinition<'a> {
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateDefinition {
     /// template name
-    pub(crate) name: &'a str,
-    /// template members
-    pub(crate) members: HashMap<&'a str, MemberInfo<'a>>,
+    pub(crate) name: String,
+    /// structure handle
+    pub(crate) handle: u16,
+    /// number of bytes of structure data
+    pub(crate) struct_size: u32,
+    /// template members in declaration order
+    pub(crate) members: Vec<MemberInfo>,
 }
 
-impl TemplateDefinition<'_> {
+impl TemplateDefinition {
+    /// template name
+    #[inline]
     pub fn name(&self) -> &str {
-        self.name
+        &self.name
+    }
+
+    /// structure handle, Tag Type Parameter used in Read/Write Tag service
+    #[inline]
+    pub fn handle(&self) -> u16 {
+        self.handle
+    }
+
+    /// number of bytes of structure data to transfer in Read/Write Tag service
+    #[inline]
+    pub fn struct_size(&self) -> u32 {
+        self.struct_size
     }
-}
 
-impl<'a> Deref for TemplateDefinition<'a> {
-    type Target = HashMap<&'a str, MemberInfo<'a>>;
-    fn deref(&self) -> &Self::Target {
+    /// members in declaration order, hidden members included
+    #[inline]
+    pub fn members(&self) -> &[MemberInfo] {
         &self.members
     }
-}
 
-impl<'a> DerefMut for TemplateDefinition<'a> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.members
+    /// visible members in declaration order
+    #[inline]
+    pub fn visible_members(&self) -> impl Iterator<Item = &MemberInfo> {
+        self.members.iter().filter(|m| !m.is_hidden())
+    }
+
+    /// get member by name
+    #[inline]
+    pub fn member(&self, name: &str) -> Option<&MemberInfo> {
+        self.members.iter().find(|m| m.name == name)
+    }
+
+    /// capacity if it's a string structure: `LEN` of DINT and `DATA` of SINT array,
+    /// eg. 82 for STRING, see [`AbString`](super::AbString)
+    pub fn string_capacity(&self) -> Option<u16> {
+        if self.members.len() != 2 {
+            return None;
+        }
+        let len = self.member("LEN")?;
+        let data = self.member("DATA")?;
+        if len.type_info.type_code() == Some(0xC4)
+            && len.array_size == 0
+            && data.type_info.type_code() == Some(0xC2)
+            && data.array_size > 0
+        {
+            Some(data.array_size)
+        } else {
+            None
+        }
     }
 }
 
 /// template member definition
-#[derive(Debug, Clone, Default)]
-pub struct MemberInfo<'de> {
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemberInfo {
     /// member name
-    pub name: &'de str,
+    pub name: String,
 
     /// array_size = 0 if atomic type;
     ///
@@ -239,8 +298,22 @@ pub struct MemberInfo<'de> {
     pub offset: u32,
 }
 
+impl MemberInfo {
+    /// host member of BOOL members, named `ZZZZZZZZZZ...`
+    #[inline]
+    pub fn is_host(&self) -> bool {
+        self.name.starts_with(HOST_MEMBER_PREFIX)
+    }
+
+    /// hidden member, eg. host member of BOOL members
+    #[inline]
+    pub fn is_hidden(&self) -> bool {
+        self.is_host() || self.name.starts_with("__")
+    }
+}
+
 /// template object
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Template {
     pub instance_id: u16,
     /// structure handle, Tag Type Parameter used in Read/Write Tag service
@@ -263,8 +336,9 @@ impl<'de> Decode<'de> for Template {
         decoder.ensure_size(28)?;
         let count = decoder.decode_u16(); // buf[0..2]
         if count != 4 {
-            return Err(Error::custom(
-                "template - unexpected count of items returned",
+            return Err(Error::invalid_value(
+                format_args!("{} attributes of template", count),
+                "4 attributes of template",
             ));
         }
 
@@ -274,7 +348,10 @@ impl<'de> Decode<'de> for Template {
         let struct_size: u32 = decode_attr(&mut decoder, 5)?;
 
         if decoder.buf().has_remaining() {
-            return Err(Error::custom("template - too much data to decode"));
+            return Err(Error::invalid_length(
+                decoder.buf().remaining(),
+                "no data after template attributes",
+            ));
         }
         Ok(Self {
             instance_id: 0,
@@ -294,25 +371,25 @@ where
     let id = buf.decode_u16();
     let status = buf.decode_u16();
     if status != 0 {
-        return Err(Error::custom(format!(
-            "attribute - bad attribute[{}] status: {:#0x}",
-            id, status
-        )));
+        return Err(Error::invalid_value(
+            format_args!("attribute[{}] status {:#0x}", id, status),
+            "attribute status 0",
+        ));
     }
     if attr_id != id {
-        return Err(Error::custom(format!(
-            "attribute -unexpected attribute[{}]",
-            id
-        )));
+        return Err(Error::invalid_value(
+            format_args!("attribute[{}]", id),
+            format_args!("attribute[{}]", attr_id),
+        ));
     }
     buf.decode_any()
 }
 
-fn decode_definition<'de>(
-    buf: &'de mut BytesMut,
+fn decode_definition(
+    buf: &mut BytesMut,
     member_count: u16,
-) -> Result<TemplateDefinition<'de>, ClientError> {
-    let mut members: SmallVec<[MemberInfo<'_>; 8]> = SmallVec::with_capacity(member_count as usize);
+) -> Result<TemplateDefinition, ClientError> {
+    let mut members: Vec<MemberInfo> = Vec::with_capacity(member_count as usize);
     for _ in 0..member_count {
         let item = MemberInfo {
             name: Default::default(),
@@ -322,20 +399,20 @@ fn decode_definition<'de>(
         };
         members.push(item);
     }
-    let mut strings = (&buf[..]).split(|v| *v == 0).map(decode_name);
+    let mut strings = buf[..].split(|v| *v == 0).map(decode_name);
     let mut get_name = || {
-        strings.next().ok_or_else(|| {
-            ClientError::custom("read template - unexpected eof while decoding names")
-        })
+        strings
+            .next()
+            .ok_or_else(|| ClientError::invalid_length(0, "name of template or member"))
     };
-    let name = get_name()?;
-    for index in 0..member_count {
-        let name = get_name()?;
-        members[index as usize].name = name;
+    let name = get_name()?.to_owned();
+    for member in members.iter_mut() {
+        member.name = get_name()?.to_owned();
     }
     Ok(TemplateDefinition {
         name,
-        members: members.drain(..).map(|item| (item.name, item)).collect(),
+        members,
+        ..Default::default()
     })
 }
 
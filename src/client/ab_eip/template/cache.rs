@@ -0,0 +1,411 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::{AbTemplateService, MemberInfo, Template, TemplateDefinition};
+use crate::{
+    cip::{epath::EPath, service::CommonServices, service::MessageService},
+    client::ab_eip::symbol::SymbolType,
+    ClientError,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rseip_core::Error;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// file header of persisted templates
+const FILE_MAGIC: &[u8; 8] = b"RSEIPTPL";
+const FILE_VERSION: u8 = 1;
+
+/// Logix controller object
+const CONTROLLER_CLASS: u16 = 0xAC;
+/// program change count of the controller object, UDINT
+const CHANGE_COUNT_ATTRIBUTE: u16 = 0x0A;
+
+/// identify the controller and its program, templates are valid as long as the key does not change.
+///
+/// the cache does not detect program changes by itself; [`CacheKey::read`] the key again
+/// and [`TemplateCache::set_key`] before using cached templates, eg. after reconnected,
+/// otherwise stale templates may be used after a download or online edits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// controller serial number, see Identity object attribute 6
+    pub serial_number: u32,
+    /// program change count, changes after download or online edits
+    pub change_count: u32,
+}
+
+impl CacheKey {
+    #[inline]
+    pub fn new(serial_number: u32, change_count: u32) -> Self {
+        Self {
+            serial_number,
+            change_count,
+        }
+    }
+
+    /// read the serial number from the Identity object,
+    /// and the change count from the Logix controller object
+    pub async fn read<C>(client: &mut C) -> Result<Self, ClientError>
+    where
+        C: MessageService<Error = ClientError>,
+    {
+        let path = EPath::default()
+            .with_class(1)
+            .with_instance(1)
+            .with_attribute(6);
+        let serial_number: u32 = client.get_attribute_single(path).await?;
+        let path = EPath::default()
+            .with_class(CONTROLLER_CLASS)
+            .with_instance(1)
+            .with_attribute(CHANGE_COUNT_ATTRIBUTE);
+        let change_count: u32 = client.get_attribute_single(path).await?;
+        Ok(Self::new(serial_number, change_count))
+    }
+
+    /// file name for persisted templates
+    #[inline]
+    fn file_name(&self) -> String {
+        format!(
+            "templates-{:08X}-{:08X}.bin",
+            self.serial_number, self.change_count
+        )
+    }
+}
+
+/// template with its definition
+#[derive(Debug, Clone)]
+pub struct CachedTemplate {
+    pub template: Template,
+    pub definition: TemplateDefinition,
+}
+
+/// cache in front of [`AbTemplateService`], templates are stored by instance id and structure handle
+#[derive(Debug, Default)]
+pub struct TemplateCache {
+    key: Option<CacheKey>,
+    items: HashMap<u16, CachedTemplate>,
+    /// structure handle -> instance id
+    handles: HashMap<u16, u16>,
+}
+
+impl TemplateCache {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// cache for the specified controller
+    #[inline]
+    pub fn with_key(key: CacheKey) -> Self {
+        Self {
+            key: Some(key),
+            ..Default::default()
+        }
+    }
+
+    /// cache key if any
+    #[inline]
+    pub fn key(&self) -> Option<CacheKey> {
+        self.key
+    }
+
+    /// set cache key; all templates are invalidated if the key changes.
+    ///
+    /// returns true if invalidated
+    pub fn set_key(&mut self, key: CacheKey) -> bool {
+        match self.key.replace(key) {
+            Some(prev) if prev == key => false,
+            _ => {
+                self.clear();
+                true
+            }
+        }
+    }
+
+    /// number of cached templates
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// get template by instance id
+    #[inline]
+    pub fn get(&self, instance_id: u16) -> Option<&CachedTemplate> {
+        self.items.get(&instance_id)
+    }
+
+    /// get template by structure handle
+    #[inline]
+    pub fn get_by_handle(&self, handle: u16) -> Option<&CachedTemplate> {
+        self.handles
+            .get(&handle)
+            .and_then(|instance_id| self.items.get(instance_id))
+    }
+
+    /// put template into the cache
+    pub fn insert(&mut self, template: Template, definition: TemplateDefinition) {
+        self.invalidate(template.instance_id);
+        self.handles.insert(template.handle, template.instance_id);
+        self.items.insert(
+            template.instance_id,
+            CachedTemplate {
+                template,
+                definition,
+            },
+        );
+    }
+
+    /// get template by instance id, fetch it from the controller if not cached
+    pub async fn fetch<C>(
+        &mut self,
+        client: &mut C,
+        instance_id: u16,
+    ) -> Result<&CachedTemplate, ClientError>
+    where
+        C: MessageService<Error = ClientError>,
+    {
+        if !self.items.contains_key(&instance_id) {
+            let template = client.find_template(instance_id).await?;
+            let definition = {
+                let mut req = client.read_template(&template);
+                req.call().await?
+            };
+            self.insert(template, definition);
+        }
+        Ok(&self.items[&instance_id])
+    }
+
+    /// remove template by instance id
+    pub fn invalidate(&mut self, instance_id: u16) -> Option<CachedTemplate> {
+        let res = self.items.remove(&instance_id)?;
+        self.handles.remove(&res.template.handle);
+        Some(res)
+    }
+
+    /// remove template by structure handle
+    pub fn invalidate_handle(&mut self, handle: u16) -> Option<CachedTemplate> {
+        let instance_id = self.handles.get(&handle).copied()?;
+        self.invalidate(instance_id)
+    }
+
+    /// remove all templates
+    #[inline]
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.handles.clear();
+    }
+
+    /// file path of persisted templates for the key
+    #[inline]
+    pub fn file_path(dir: impl AsRef<Path>, key: CacheKey) -> PathBuf {
+        dir.as_ref().join(key.file_name())
+    }
+
+    /// load persisted templates from the directory;
+    /// returns empty cache if nothing persisted for the key
+    pub fn load(dir: impl AsRef<Path>, key: CacheKey) -> Result<Self, ClientError> {
+        let mut res = Self::with_key(key);
+        let data = match fs::read(Self::file_path(dir, key)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(res),
+            Err(e) => return Err(e.into()),
+        };
+        let mut buf = Bytes::from(data);
+        ensure_size(&buf, FILE_MAGIC.len() + 1 + 4)?;
+        if &buf.split_to(FILE_MAGIC.len())[..] != FILE_MAGIC || buf.get_u8() != FILE_VERSION {
+            return Err(Error::invalid_value("file header", "template cache file"));
+        }
+        let count = buf.get_u32_le();
+        for _ in 0..count {
+            let (template, definition) = decode_entry(&mut buf)?;
+            res.insert(template, definition);
+        }
+        Ok(res)
+    }
+
+    /// persist templates to the directory, the file is named by the cache key
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<(), ClientError> {
+        let key = self
+            .key
+            .ok_or_else(|| ClientError::custom("template cache - need to initialize `key`"))?;
+        let mut buf = BytesMut::new();
+        buf.put_slice(FILE_MAGIC);
+        buf.put_u8(FILE_VERSION);
+        buf.put_u32_le(self.items.len() as u32);
+        for item in self.items.values() {
+            encode_entry(&mut buf, item);
+        }
+        fs::create_dir_all(dir.as_ref())?;
+        fs::write(Self::file_path(dir, key), &buf[..])?;
+        Ok(())
+    }
+}
+
+fn encode_entry(buf: &mut BytesMut, item: &CachedTemplate) {
+    let Template {
+        instance_id,
+        handle,
+        member_count,
+        object_size,
+        struct_size,
+    } = item.template;
+    buf.put_u16_le(instance_id);
+    buf.put_u16_le(handle);
+    buf.put_u16_le(member_count);
+    buf.put_u32_le(object_size);
+    buf.put_u32_le(struct_size);
+    encode_str(buf, item.definition.name());
+    buf.put_u16_le(item.definition.members().len() as u16);
+    for member in item.definition.members() {
+        encode_str(buf, &member.name);
+        buf.put_u16_le(member.array_size);
+        buf.put_u16_le(member.type_info.0);
+        buf.put_u32_le(member.offset);
+    }
+}
+
+fn decode_entry(buf: &mut Bytes) -> Result<(Template, TemplateDefinition), ClientError> {
+    ensure_size(buf, 14)?;
+    let template = Template {
+        instance_id: buf.get_u16_le(),
+        handle: buf.get_u16_le(),
+        member_count: buf.get_u16_le(),
+        object_size: buf.get_u32_le(),
+        struct_size: buf.get_u32_le(),
+    };
+    let name = decode_str(buf)?;
+    ensure_size(buf, 2)?;
+    let count = buf.get_u16_le();
+    let mut members = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = decode_str(buf)?;
+        ensure_size(buf, 8)?;
+        members.push(MemberInfo {
+            name,
+            array_size: buf.get_u16_le(),
+            type_info: SymbolType(buf.get_u16_le()),
+            offset: buf.get_u32_le(),
+        });
+    }
+    let definition = TemplateDefinition {
+        name,
+        handle: template.handle,
+        struct_size: template.struct_size,
+        members,
+    };
+    Ok((template, definition))
+}
+
+#[inline]
+fn encode_str(buf: &mut BytesMut, s: &str) {
+    buf.put_u16_le(s.len() as u16);
+    buf.put_slice(s.as_bytes());
+}
+
+#[inline]
+fn decode_str(buf: &mut Bytes) -> Result<String, ClientError> {
+    ensure_size(buf, 2)?;
+    let len = buf.get_u16_le() as usize;
+    ensure_size(buf, len)?;
+    String::from_utf8(buf.split_to(len).to_vec())
+        .map_err(|_| Error::invalid_value("non utf8 bytes", "template or member name"))
+}
+
+#[inline]
+fn ensure_size(buf: &Bytes, size: usize) -> Result<(), ClientError> {
+    if buf.remaining() < size {
+        return Err(Error::invalid_length(
+            buf.remaining(),
+            format_args!("at least {}", size),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        mock::{MockDriver, MockEndpoint},
+        Client,
+    };
+
+    fn definition() -> TemplateDefinition {
+        let member = MemberInfo {
+            name: "a".to_owned(),
+            array_size: 2,
+            type_info: SymbolType(0xC4),
+            offset: 4,
+        };
+        TemplateDefinition {
+            name: "MyUDT".to_owned(),
+            handle: 0x1234,
+            struct_size: 12,
+            members: vec![member],
+        }
+    }
+
+    fn template(instance_id: u16, handle: u16) -> Template {
+        Template {
+            instance_id,
+            handle,
+            member_count: 1,
+            object_size: 10,
+            struct_size: 12,
+        }
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut cache = TemplateCache::with_key(CacheKey::new(1, 1));
+        cache.insert(template(0x100, 0x1234), definition());
+        assert!(cache.get_by_handle(0x1234).is_some());
+        assert!(cache.invalidate_handle(0x1234).is_some());
+        assert!(cache.get(0x100).is_none());
+
+        cache.insert(template(0x100, 0x1234), definition());
+        assert!(!cache.set_key(CacheKey::new(1, 1)));
+        assert!(cache.set_key(CacheKey::new(1, 2)));
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_key() {
+        let endpoint = MockEndpoint {
+            attributes: &[(0x01, 0x06, 0xABCD), (0xAC, 0x0A, 7)],
+            ..Default::default()
+        };
+        let mut client = Client::<MockDriver>::new(endpoint);
+        let key = CacheKey::read(&mut client).await.unwrap();
+        assert_eq!(key, CacheKey::new(0xABCD, 7));
+    }
+
+    #[test]
+    fn test_persist() {
+        let dir = std::env::temp_dir().join(format!("rseip-test-{}", std::process::id()));
+        let key = CacheKey::new(0xABCD, 7);
+        let mut cache = TemplateCache::with_key(key);
+        cache.insert(template(0x100, 0x1234), definition());
+        cache.save(&dir).unwrap();
+
+        let res = TemplateCache::load(&dir, key).unwrap();
+        let item = res.get_by_handle(0x1234).unwrap();
+        assert_eq!(item.template, template(0x100, 0x1234));
+        assert_eq!(item.definition, definition());
+
+        let res = TemplateCache::load(&dir, CacheKey::new(0xABCD, 8)).unwrap();
+        assert!(res.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
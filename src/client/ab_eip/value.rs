@@ -4,6 +4,7 @@
 // Copyright: 2021, Joylei <leingliu@gmail.com>
 // License: MIT
 
+use super::STRING_STRUCTURE_HANDLE;
 use crate::ClientError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use core::marker::PhantomData;
@@ -38,6 +39,76 @@ pub type LINT = i64;
 #[allow(clippy::upper_case_acronyms)]
 #[allow(unused)]
 pub type REAL = f32;
+/// atomic data type: USINT, unsigned 8-bit integer
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type USINT = u8;
+/// atomic data type: UINT, unsigned 16-bit integer
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type UINT = u16;
+/// atomic data type: UDINT, unsigned 32-bit integer
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type UDINT = u32;
+/// atomic data type: ULINT, unsigned 64-bit integer
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type ULINT = u64;
+/// atomic data type: LREAL, 64-bit float
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type LREAL = f64;
+/// atomic data type: BYTE, 8-bit boolean array
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type BYTE = u8;
+/// atomic data type: WORD, 16-bit boolean array
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type WORD = u16;
+/// atomic data type: LWORD, 64-bit boolean array
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type LWORD = u64;
+/// atomic data type: STIME, synchronous time in microseconds
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type STIME = i32;
+/// atomic data type: DATE, days since 1972-01-01
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type DATE = u16;
+/// atomic data type: TIME_OF_DAY, milliseconds since midnight
+#[allow(clippy::upper_case_acronyms)]
+#[allow(non_camel_case_types)]
+#[allow(unused)]
+pub type TIME_OF_DAY = u32;
+/// atomic data type: DATE_AND_TIME, (TIME_OF_DAY, DATE)
+#[allow(clippy::upper_case_acronyms)]
+#[allow(non_camel_case_types)]
+#[allow(unused)]
+pub type DATE_AND_TIME = (u32, u16);
+/// atomic data type: FTIME, duration in microseconds
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type FTIME = i32;
+/// atomic data type: LTIME, duration in microseconds
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type LTIME = i64;
+/// atomic data type: ITIME, duration in milliseconds
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type ITIME = i16;
+/// atomic data type: TIME, duration in milliseconds
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type TIME = i32;
+/// atomic data type: ENGUNIT, engineering units
+#[allow(clippy::upper_case_acronyms)]
+#[allow(unused)]
+pub type ENGUNIT = u16;
 
 /// tag type for AB PLC
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -56,26 +127,157 @@ pub enum TagType {
     Lint,
     /// atomic data type: REAL, 32-bit float
     Real,
+    /// atomic data type: USINT, unsigned 8-bit integer
+    Usint,
+    /// atomic data type: UINT, unsigned 16-bit integer
+    Uint,
+    /// atomic data type: UDINT, unsigned 32-bit integer
+    Udint,
+    /// atomic data type: ULINT, unsigned 64-bit integer
+    Ulint,
+    /// atomic data type: LREAL, 64-bit float
+    Lreal,
+    /// atomic data type: STIME, synchronous time
+    Stime,
+    /// atomic data type: DATE
+    Date,
+    /// atomic data type: TIME_OF_DAY
+    TimeOfDay,
+    /// atomic data type: DATE_AND_TIME
+    DateAndTime,
+    /// atomic data type: STRING, 1-byte characters with UINT length;
+    /// not the Logix STRING structure, see [`TagType::STRING`]
+    String,
+    /// atomic data type: BYTE, 8-bit boolean array
+    Byte,
+    /// atomic data type: WORD, 16-bit boolean array
+    Word,
+    /// atomic data type: LWORD, 64-bit boolean array
+    Lword,
+    /// atomic data type: STRING2, 2-byte characters with UINT length
+    String2,
+    /// atomic data type: FTIME, duration high resolution
+    Ftime,
+    /// atomic data type: LTIME, duration long
+    Ltime,
+    /// atomic data type: ITIME, duration short
+    Itime,
+    /// atomic data type: STRINGN, N-byte characters
+    StringN,
+    /// atomic data type: SHORT_STRING, 1-byte characters with USINT length
+    ShortString,
+    /// atomic data type: TIME, duration in milliseconds
+    Time,
+    /// atomic data type: EPATH
+    Epath,
+    /// atomic data type: ENGUNIT, engineering units
+    EngUnit,
+    /// atomic data type: STRINGI, international character string
+    StringI,
     /// structured tag
     Structure(u16),
 }
 
 impl TagType {
+    /// built-in STRING structure
+    pub const STRING: TagType = TagType::Structure(STRING_STRUCTURE_HANDLE);
+
     /// two bytes type code
     #[inline]
     pub fn type_code(&self) -> u16 {
         match self {
             Self::Bool => 0xC1,
-            Self::Dword => 0xD3,
             Self::Sint => 0xC2,
             Self::Int => 0xC3,
             Self::Dint => 0xC4,
             Self::Lint => 0xC5,
+            Self::Usint => 0xC6,
+            Self::Uint => 0xC7,
+            Self::Udint => 0xC8,
+            Self::Ulint => 0xC9,
             Self::Real => 0xCA,
+            Self::Lreal => 0xCB,
+            Self::Stime => 0xCC,
+            Self::Date => 0xCD,
+            Self::TimeOfDay => 0xCE,
+            Self::DateAndTime => 0xCF,
+            Self::String => 0xD0,
+            Self::Byte => 0xD1,
+            Self::Word => 0xD2,
+            Self::Dword => 0xD3,
+            Self::Lword => 0xD4,
+            Self::String2 => 0xD5,
+            Self::Ftime => 0xD6,
+            Self::Ltime => 0xD7,
+            Self::Itime => 0xD8,
+            Self::StringN => 0xD9,
+            Self::ShortString => 0xDA,
+            Self::Time => 0xDB,
+            Self::Epath => 0xDC,
+            Self::EngUnit => 0xDD,
+            Self::StringI => 0xDE,
             Self::Structure { .. } => 0x02A0,
         }
     }
 
+    /// atomic type for the type code; none if unknown or structure
+    pub fn from_type_code(type_code: u16) -> Option<Self> {
+        let v = match type_code {
+            0xC1 => Self::Bool,
+            0xC2 => Self::Sint,
+            0xC3 => Self::Int,
+            0xC4 => Self::Dint,
+            0xC5 => Self::Lint,
+            0xC6 => Self::Usint,
+            0xC7 => Self::Uint,
+            0xC8 => Self::Udint,
+            0xC9 => Self::Ulint,
+            0xCA => Self::Real,
+            0xCB => Self::Lreal,
+            0xCC => Self::Stime,
+            0xCD => Self::Date,
+            0xCE => Self::TimeOfDay,
+            0xCF => Self::DateAndTime,
+            0xD0 => Self::String,
+            0xD1 => Self::Byte,
+            0xD2 => Self::Word,
+            0xD3 => Self::Dword,
+            0xD4 => Self::Lword,
+            0xD5 => Self::String2,
+            0xD6 => Self::Ftime,
+            0xD7 => Self::Ltime,
+            0xD8 => Self::Itime,
+            0xD9 => Self::StringN,
+            0xDA => Self::ShortString,
+            0xDB => Self::Time,
+            0xDC => Self::Epath,
+            0xDD => Self::EngUnit,
+            0xDE => Self::StringI,
+            _ => return None,
+        };
+        Some(v)
+    }
+
+    /// number of bytes of one element; none if variable length or structure
+    pub fn size(&self) -> Option<usize> {
+        let v = match self {
+            Self::Bool | Self::Sint | Self::Usint | Self::Byte => 1,
+            Self::Int | Self::Uint | Self::Word | Self::Date | Self::Itime | Self::EngUnit => 2,
+            Self::Dint
+            | Self::Udint
+            | Self::Real
+            | Self::Dword
+            | Self::Stime
+            | Self::TimeOfDay
+            | Self::Ftime
+            | Self::Time => 4,
+            Self::DateAndTime => 6,
+            Self::Lint | Self::Ulint | Self::Lreal | Self::Lword | Self::Ltime => 8,
+            _ => return None,
+        };
+        Some(v)
+    }
+
     /// is it a structure
     pub fn is_structure(&self) -> bool {
         match self {
@@ -89,6 +291,12 @@ impl TagType {
         !self.is_structure()
     }
 
+    /// is it the built-in STRING structure
+    #[inline]
+    pub fn is_string(&self) -> bool {
+        *self == Self::STRING
+    }
+
     /// get structure handle if it's a structure
     pub fn structure_handle(&self) -> Option<u16> {
         match self {
@@ -106,31 +314,13 @@ impl Encode for TagType {
         encoder: &mut A,
     ) -> Result<(), A::Error> {
         match self {
-            Self::Bool => {
-                encoder.encode_u16(0xC1, buf)?;
-            }
-            Self::Sint => {
-                encoder.encode_u16(0xC2, buf)?;
-            }
-            Self::Int => {
-                encoder.encode_u16(0xC3, buf)?;
-            }
-            Self::Dint => {
-                encoder.encode_u16(0xC4, buf)?;
-            }
-            Self::Real => {
-                encoder.encode_u16(0xCA, buf)?;
-            }
-            Self::Dword => {
-                encoder.encode_u16(0xD3, buf)?;
-            }
-            Self::Lint => {
-                encoder.encode_u16(0xC5, buf)?;
-            }
             Self::Structure(handle) => {
-                encoder.encode(&[0xA0, 0x02], buf)?;
+                encoder.encode([0xA0, 0x02], buf)?;
                 encoder.encode_u16(*handle, buf)?;
             }
+            _ => {
+                encoder.encode_u16(self.type_code(), buf)?;
+            }
         }
         Ok(())
     }
@@ -153,23 +343,13 @@ impl<'de> Decode<'de> for TagType {
         decoder.ensure_size(3)?;
         let type_code = decoder.decode_u16();
         let val = match type_code {
-            0xC2 => TagType::Sint,
-            0xC3 => TagType::Int,
-            0xC4 => TagType::Dint,
-            0xCA => TagType::Real,
-            0xD3 => TagType::Dword,
-            0xC5 => TagType::Lint,
-            0xC1 => TagType::Bool,
             0x02A0 => {
                 decoder.ensure_size(2)?;
                 TagType::Structure(decoder.decode_u16())
             }
-            _ => {
-                return Err(Error::custom(format!(
-                    "unexpected type code: {}",
-                    type_code
-                )))
-            }
+            _ => TagType::from_type_code(type_code).ok_or_else(|| {
+                D::Error::invalid_value(format_args!("type code {:#06x}", type_code), "tag type")
+            })?,
         };
         Ok(val)
     }
@@ -501,3 +681,36 @@ impl<'de> Decode<'de> for TagValueIter {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientError;
+
+    #[test]
+    fn test_tag_type_roundtrip() {
+        for code in 0xC1..=0xDE {
+            let tag_type = TagType::from_type_code(code).unwrap();
+            assert_eq!(tag_type.type_code(), code);
+            let mut buf = LittleEndianEncoder::<ClientError>::new()
+                .encode_to_bytes(tag_type)
+                .unwrap()
+                .to_vec();
+            assert_eq!(buf, code.to_le_bytes());
+            // followed by data
+            buf.push(0);
+            let res: TagType = LittleEndianDecoder::<ClientError>::new(buf.into())
+                .decode_any()
+                .unwrap();
+            assert_eq!(res, tag_type);
+        }
+        assert_eq!(TagType::from_type_code(0x02A0), None);
+        assert_eq!(TagType::Lreal.size(), Some(8));
+        assert_eq!(TagType::String.size(), None);
+
+        let res: Result<TagType, ClientError> =
+            LittleEndianDecoder::<ClientError>::new(Bytes::from_static(&[0xFF, 0x00, 0x00]))
+                .decode_any();
+        assert_eq!(res.unwrap_err().kind(), crate::ErrorKind::Decode);
+    }
+}
@@ -0,0 +1,380 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+//! driver for tests, replies are made up without any transport
+
+use super::*;
+use crate::cip::{
+    connection::{ForwardCloseReply, ForwardCloseRequest, ForwardOpenReply},
+    service::request::UnconnectedSend,
+};
+use crate::client::ab_eip::TagValue;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use core::hash::{Hash, Hasher};
+use core::ptr;
+use core::str;
+use rseip_core::codec::{Decoder, LittleEndianDecoder, LittleEndianEncoder};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// behavior of [`MockService`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct MockEndpoint {
+    /// heartbeat fails if not healthy
+    pub healthy: bool,
+    /// general and extended status of Large Forward Open reply if rejected
+    pub large_open_status: Option<(u8, Option<u16>)>,
+    /// shared by services built from the endpoint
+    pub trace: Option<Arc<MockTrace>>,
+    /// symbols listed by Get Instance Attribute List:
+    /// program scope or empty for controller scope, instance id, name and symbol type
+    pub symbols: &'static [(&'static str, u16, &'static str, u16)],
+    /// UDINT attributes replied to Get Attribute Single: class, attribute and value
+    pub attributes: &'static [(u16, u16, u32)],
+}
+
+impl Default for MockEndpoint {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            large_open_status: None,
+            trace: None,
+            symbols: &[],
+            attributes: &[],
+        }
+    }
+}
+
+/// sessions and requests seen by services, one per endpoint
+#[derive(Debug, Default)]
+pub(crate) struct MockTrace {
+    /// number of registered sessions
+    pub sessions: AtomicUsize,
+    /// service codes of unconnected requests, including failed ones
+    pub requests: Mutex<Vec<u8>>,
+    /// number of next session registrations or requests failing as if the transport dropped
+    pub drops: AtomicUsize,
+    /// tag values replied to Read Tag requests of Multiple Service Packets, status 0x04 if missing
+    pub values: Mutex<HashMap<&'static str, TagValue<Bytes>>>,
+    /// tag names read by each Multiple Service Packet
+    pub reads: Mutex<Vec<Vec<String>>>,
+    /// encoded Unconnected Send of each unconnected request
+    pub unconnected: Mutex<Vec<Bytes>>,
+    /// replies of unconnected requests are delayed if any
+    pub delay: Mutex<Option<Duration>>,
+}
+
+impl MockTrace {
+    /// set or remove the value of a tag
+    pub fn set_value(&self, tag: &'static str, value: Option<TagValue<Bytes>>) {
+        let mut values = self.values.lock().unwrap();
+        match value {
+            Some(v) => values.insert(tag, v),
+            None => values.remove(tag),
+        };
+    }
+
+    /// tag names read by each Multiple Service Packet since last call
+    pub fn take_reads(&self) -> Vec<Vec<String>> {
+        core::mem::take(&mut *self.reads.lock().unwrap())
+    }
+
+    /// the next session registration or request fails?
+    fn take_drop(&self) -> bool {
+        self.drops
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |v| v.checked_sub(1))
+            .is_ok()
+    }
+}
+
+impl PartialEq for MockTrace {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl Eq for MockTrace {}
+
+impl Hash for MockTrace {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ptr::hash(self, state)
+    }
+}
+
+pub(crate) struct MockDriver;
+
+#[derive(Debug)]
+pub(crate) struct MockService {
+    endpoint: MockEndpoint,
+    /// session registered
+    opened: bool,
+}
+
+impl MockService {
+    /// Get Instance Attribute List of name and symbol type, all symbols in one reply
+    fn list_symbols(&self, path: &[u8], buf: &mut BytesMut) {
+        // [symbolic segment] | class segment | instance segment
+        let (scope, path) = match path {
+            [0x91, len, rest @ ..] => {
+                let len = *len as usize;
+                let scope = str::from_utf8(&rest[..len]).unwrap();
+                (scope, &rest[len + len % 2..])
+            }
+            _ => ("", path),
+        };
+        let start_instance = match path {
+            [0x20, 0x6B, 0x24, v, ..] => *v as u16,
+            [0x20, 0x6B, 0x25, 0, lo, hi, ..] => u16::from_le_bytes([*lo, *hi]),
+            _ => panic!("unexpected symbol path"),
+        };
+        let symbols = self
+            .endpoint
+            .symbols
+            .iter()
+            .filter(|v| v.0 == scope && v.1 >= start_instance);
+        for (_, id, name, symbol_type) in symbols {
+            buf.put_u32_le(*id as u32);
+            buf.put_u16_le(name.len() as u16);
+            buf.put_slice(name.as_bytes());
+            buf.put_u16_le(*symbol_type);
+        }
+    }
+
+    /// Multiple Service Packet of Read Tag requests of symbolic paths
+    fn read_tags(&self, mut data: Bytes, buf: &mut BytesMut) {
+        let trace = self.endpoint.trace.as_ref().expect("trace for tag values");
+        let count = data.get_u16_le() as usize;
+        data.advance(2 * count);
+        let mut names = Vec::with_capacity(count);
+        let mut replies = Vec::with_capacity(count);
+        for _ in 0..count {
+            // service | path size in words | path | element count
+            let service = data.get_u8();
+            assert_eq!(service, 0x4C, "unexpected service");
+            let words = data.get_u8() as usize;
+            let path = data.split_to(words * 2);
+            data.advance(2);
+            let name = match path[..] {
+                [0x91, len, ref rest @ ..] => str::from_utf8(&rest[..len as usize]).unwrap(),
+                _ => panic!("unexpected tag path"),
+            };
+            let mut reply = BytesMut::new();
+            match trace.values.lock().unwrap().get(name) {
+                Some(value) => {
+                    reply.put_slice(&[service | 0x80, 0, 0, 0]);
+                    reply.put_u16_le(value.tag_type.type_code());
+                    reply.put_slice(&value.value);
+                }
+                None => reply.put_slice(&[service | 0x80, 0, 0x04, 0]),
+            }
+            names.push(name.to_owned());
+            replies.push(reply);
+        }
+        trace.reads.lock().unwrap().push(names);
+
+        buf.put_u16_le(count as u16);
+        let mut offset = 2 + 2 * count;
+        for reply in replies.iter() {
+            buf.put_u16_le(offset as u16);
+            offset += reply.len();
+        }
+        for reply in replies {
+            buf.put_slice(&reply);
+        }
+    }
+
+    /// Get Attribute Single of instance 1, 8-bit segments only; no data if not listed
+    fn get_attribute(&self, path: &[u8], buf: &mut BytesMut) {
+        if let [0x20, class, 0x24, 1, 0x30, attribute] = *path {
+            let value = self
+                .endpoint
+                .attributes
+                .iter()
+                .find(|v| v.0 == class as u16 && v.1 == attribute as u16);
+            if let Some((_, _, value)) = value {
+                buf.put_u32_le(*value);
+            }
+        }
+    }
+
+    /// fail as if the transport dropped if any drops left
+    fn drop_transport(&self) -> Result<()> {
+        match self.endpoint.trace {
+            Some(ref trace) if trace.take_drop() => {
+                Err(io::Error::from(io::ErrorKind::ConnectionReset).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Driver for MockDriver {
+    type Endpoint = MockEndpoint;
+    type Service = MockService;
+
+    fn build_service(endpoint: Self::Endpoint) -> BoxFuture<'static, Result<Self::Service>> {
+        Box::pin(async move {
+            Ok(MockService {
+                endpoint,
+                opened: false,
+            })
+        })
+    }
+}
+
+#[inline]
+fn decode_reply<'de, R: Decode<'de>>(buf: BytesMut) -> Result<R> {
+    LittleEndianDecoder::<ClientError>::new(buf.freeze()).decode_any()
+}
+
+#[async_trait::async_trait]
+impl Service for MockService {
+    fn is_open(&self) -> bool {
+        self.opened
+    }
+
+    async fn open(&mut self) -> Result<()> {
+        if !self.opened {
+            self.drop_transport()?;
+            if let Some(ref trace) = self.endpoint.trace {
+                trace.sessions.fetch_add(1, Ordering::SeqCst);
+            }
+            self.opened = true;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn heartbeat(&mut self) -> Result<()> {
+        if self.endpoint.healthy {
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe).into())
+        }
+    }
+
+    async fn unconnected_send<'de, CP, P, D, R>(
+        &mut self,
+        request: UnconnectedSend<CP, MessageRequest<P, D>>,
+    ) -> Result<R>
+    where
+        CP: Encode + Send + Sync,
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + 'static,
+    {
+        if let Some(ref trace) = self.endpoint.trace {
+            trace
+                .requests
+                .lock()
+                .unwrap()
+                .push(request.data.service_code);
+            let data = LittleEndianEncoder::<ClientError>::new().encode_to_bytes(&request)?;
+            trace.unconnected.lock().unwrap().push(data);
+        }
+        self.drop_transport()?;
+        let delay = self
+            .endpoint
+            .trace
+            .as_ref()
+            .and_then(|v| *v.delay.lock().unwrap());
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        let mut buf = BytesMut::new();
+        buf.put_slice(&[request.data.service_code | 0x80, 0, 0, 0]);
+        if request.data.service_code == 0x55 {
+            let path =
+                LittleEndianEncoder::<ClientError>::new().encode_to_bytes(&request.data.path)?;
+            self.list_symbols(&path, &mut buf);
+        } else if request.data.service_code == 0x0E {
+            let path =
+                LittleEndianEncoder::<ClientError>::new().encode_to_bytes(&request.data.path)?;
+            self.get_attribute(&path, &mut buf);
+        } else if request.data.service_code == 0x0A {
+            let data =
+                LittleEndianEncoder::<ClientError>::new().encode_to_bytes(&request.data.data)?;
+            self.read_tags(data, &mut buf);
+        }
+        decode_reply(buf)
+    }
+
+    async fn connected_send<'de, P, D, R>(
+        &mut self,
+        _connection_id: u32,
+        _sequence_number: u16,
+        request: MessageRequest<P, D>,
+    ) -> Result<R>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + 'static,
+    {
+        let mut buf = BytesMut::new();
+        buf.put_slice(&[request.service_code | 0x80, 0, 0, 0]);
+        decode_reply(buf)
+    }
+
+    async fn forward_open<P>(&mut self, request: OpenOptions<P>) -> Result<ForwardOpenReply>
+    where
+        P: Encode + Send + Sync,
+    {
+        let mut buf = BytesMut::new();
+        buf.put_u8(request.service_code() | 0x80);
+        buf.put_u8(0);
+        match self.endpoint.large_open_status {
+            Some((general, extended)) if request.large_open => {
+                buf.put_u8(general);
+                match extended {
+                    Some(v) => {
+                        buf.put_u8(1);
+                        buf.put_u16_le(v);
+                    }
+                    None => buf.put_u8(0),
+                }
+                // no data if the service not supported
+                if general != 0x08 {
+                    buf.put_u16_le(request.connection_serial_number);
+                    buf.put_u16_le(request.vendor_id);
+                    buf.put_u32_le(request.originator_serial_number);
+                }
+            }
+            _ => {
+                buf.put_slice(&[0, 0]);
+                buf.put_u32_le(1); // o_t connection id
+                buf.put_u32_le(request.t_o_connection_id);
+                buf.put_u16_le(request.connection_serial_number);
+                buf.put_u16_le(request.vendor_id);
+                buf.put_u32_le(request.originator_serial_number);
+                buf.put_u32_le(request.o_t_rpi);
+                buf.put_u32_le(request.t_o_rpi);
+                buf.put_slice(&[0, 0]); // app reply size | reserved
+            }
+        }
+        decode_reply(buf)
+    }
+
+    async fn forward_close<P>(
+        &mut self,
+        _request: ForwardCloseRequest<P>,
+    ) -> Result<ForwardCloseReply>
+    where
+        P: Encode + Send + Sync,
+    {
+        Err(Error::custom("mock - forward close not supported"))
+    }
+}
@@ -8,6 +8,12 @@
 pub mod ab_eip;
 /// generic EIP
 pub mod eip;
+#[cfg(test)]
+mod mock;
+mod pool;
+mod reconnect;
+mod shared;
+mod timeouts;
 
 use crate::{adapters::Service, ClientError, Result};
 pub use ab_eip::{AbEipClient, AbEipConnection, AbEipDriver, AbService, AbTemplateService};
@@ -18,10 +24,13 @@ use core::{
 };
 pub use eip::*;
 use futures_util::future::BoxFuture;
+pub use pool::{Pool, PoolKey, PoolOptions, Pooled, DEFAULT_MAX_CONNECTIONS};
+pub use reconnect::{ConnectionState, ReconnectPolicy};
+use reconnect::{Fault, Recovery};
 /// reexport
 pub use rseip_cip::connection::OpenOptions;
 use rseip_cip::{
-    connection::ForwardCloseRequest,
+    connection::{ForwardCloseRequest, ForwardOpenError},
     service::Heartbeat,
     service::{request::UnconnectedSend, MessageService},
     *,
@@ -30,7 +39,30 @@ use rseip_core::{
     codec::{Decode, Encode},
     Either, Error,
 };
-use std::{io, sync::atomic::AtomicU16};
+pub use shared::{SharedClient, DEFAULT_QUEUE_SIZE};
+use std::{io, sync::atomic::AtomicU16, time::Duration};
+use timeouts::timeout;
+pub use timeouts::Timeouts;
+use tokio::{sync::watch, time::Instant};
+
+/// max number of bytes of an unconnected message request or reply
+pub const UNCONNECTED_MESSAGE_SIZE: usize = 480;
+
+/// size limit of message request or reply for a single packet
+pub trait MessageSize {
+    /// max number of bytes of a message request or reply
+    fn max_message_size(&self) -> usize;
+}
+
+/// keep the transport or the CIP connection alive when idle;
+/// nothing is sent by itself, [`SharedClient`] sends heartbeat when idle for the interval,
+/// otherwise call [`Heartbeat::heartbeat`] periodically
+pub trait KeepAlive {
+    /// send heartbeat if idle for the interval; none if not needed
+    fn keep_alive_interval(&self) -> Option<Duration> {
+        None
+    }
+}
 
 /// driver for specified protocol
 pub trait Driver: Send + Sync {
@@ -53,6 +85,9 @@ pub struct Client<B: Driver> {
     service: Option<B::Service>,
     /// connection path
     connection_path: EPath,
+    /// reconnect policy and state events
+    recovery: Recovery,
+    timeouts: Timeouts,
 }
 
 impl<B: Driver> Client<B> {
@@ -63,6 +98,8 @@ impl<B: Driver> Client<B> {
             addr,
             service: None,
             connection_path: Default::default(),
+            recovery: Default::default(),
+            timeouts: Default::default(),
         }
     }
 
@@ -79,6 +116,31 @@ impl<B: Driver> Client<B> {
         &self.connection_path
     }
 
+    /// reconnect when the transport or the session is lost
+    #[inline]
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.recovery.set_policy(Some(policy));
+        self
+    }
+
+    /// watch state changes of the transport
+    #[inline]
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.recovery.subscribe()
+    }
+
+    /// set timeouts
+    #[inline]
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    #[inline]
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
     /// current remote endpoint, driver specific
     #[inline]
     pub fn remote_endpoint(&self) -> &B::Endpoint {
@@ -87,18 +149,52 @@ impl<B: Driver> Client<B> {
 
     #[inline]
     async fn ensure_service(&mut self) -> Result<()> {
-        if self.service.is_none() {
-            let service = B::build_service(self.addr.clone()).await?;
-            self.service = Some(service);
-        }
-        match self.service {
-            None => unreachable!(),
-            Some(ref mut service) => {
-                service.open().await?;
+        let res = timeout(self.timeouts.connect_timeout(), "connect", async {
+            if self.service.is_none() {
+                let service = B::build_service(self.addr.clone()).await?;
+                self.service = Some(service);
             }
+            match self.service {
+                None => unreachable!(),
+                Some(ref mut service) => service.open().await,
+            }
+        })
+        .await;
+        if let Err(e) = res {
+            if e.is_timeout() {
+                self.service = None;
+            }
+            return Err(e);
         }
+        self.recovery.set_state(ConnectionState::Connected);
         Ok(())
     }
+
+    /// Unconnected Send with ticks of [`Timeouts`]
+    #[inline]
+    fn unconnected_send<T>(&self, mr: T) -> UnconnectedSend<EPath, T> {
+        let mut req = UnconnectedSend::new(self.connection_path.clone(), mr);
+        (req.priority_ticks, req.timeout_ticks) = self.timeouts.ticks();
+        req
+    }
+
+    /// drop the lost transport, then reconnect if any policy;
+    /// true if reconnected
+    async fn recover(&mut self) -> bool {
+        self.service = None;
+        self.recovery.set_state(ConnectionState::Disconnected);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            if !self.recovery.wait(attempt).await {
+                return false;
+            }
+            if self.ensure_service().await.is_ok() {
+                return true;
+            }
+            self.service = None;
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -108,7 +204,18 @@ impl<B: Driver> Heartbeat for Client<B> {
     #[inline]
     async fn heartbeat(&mut self) -> Result<()> {
         if let Some(ref mut service) = self.service {
-            service.heartbeat().await?;
+            let res = timeout(
+                self.timeouts.request_timeout(),
+                "heartbeat",
+                service.heartbeat(),
+            )
+            .await;
+            if let Err(e) = res {
+                if Fault::of_unconnected(&e).is_some() {
+                    self.recover().await;
+                }
+                return Err(e);
+            }
         }
         Ok(())
     }
@@ -127,20 +234,65 @@ impl<B: Driver> MessageService for Client<B> {
         D: Encode + Send + Sync,
         R: MessageReplyInterface + Decode<'de> + 'static,
     {
-        // create service if not created
+        let mut replayed = false;
+        loop {
+            // create service if not created
+            self.ensure_service().await?;
+            let req =
+                self.unconnected_send(MessageRequest::new(mr.service_code, &mr.path, &mr.data));
+            let service = self.service.as_mut().expect("expected service");
+            let fut = service.unconnected_send(req);
+            let err = match timeout(self.timeouts.request_timeout(), "request", fut).await {
+                Ok(res) => return Ok(res),
+                Err(e) => e,
+            };
+            if Fault::of_unconnected(&err).is_none()
+                || !self.recover().await
+                || replayed
+                || !self.recovery.replay(mr.service_code)
+            {
+                return Err(err);
+            }
+            replayed = true;
+        }
+    }
+
+    /// unconnected send, all requests are sent before waiting for replies
+    #[inline]
+    async fn send_all<'de, P, D, R>(
+        &mut self,
+        requests: Vec<MessageRequest<P, D>>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
         self.ensure_service().await?;
+        let requests = requests
+            .into_iter()
+            .map(|mr| self.unconnected_send(mr))
+            .collect();
         let service = self.service.as_mut().expect("expected service");
-        let req = UnconnectedSend::new(self.connection_path.clone(), mr);
-        let res = service.unconnected_send(req).await?;
-        Ok(res)
+        let fut = service.unconnected_send_all(requests);
+        match timeout(self.timeouts.request_timeout(), "request", fut).await {
+            Ok(res) => Ok(res),
+            Err(e) => {
+                if Fault::of_unconnected(&e).is_some() {
+                    self.recover().await;
+                }
+                Err(e)
+            }
+        }
     }
 
     /// close underline transport
     #[inline]
     async fn close(&mut self) -> Result<()> {
         if let Some(mut service) = self.service.take() {
-            let _ = service.close().await;
+            let _ = timeout(self.timeouts.request_timeout(), "close", service.close()).await;
         }
+        self.recovery.set_state(ConnectionState::Disconnected);
         Ok(())
     }
 
@@ -151,7 +303,18 @@ impl<B: Driver> MessageService for Client<B> {
     }
 }
 
-/// explicit messaging connection
+impl<B: Driver> MessageSize for Client<B> {
+    #[inline]
+    fn max_message_size(&self) -> usize {
+        UNCONNECTED_MESSAGE_SIZE
+    }
+}
+
+impl<B: Driver> KeepAlive for Client<B> {}
+
+/// explicit messaging connection;
+/// to keep the CIP connection alive when idle, wrap it in [`SharedClient`],
+/// or call [`Heartbeat::heartbeat`] periodically, see [`KeepAlive`]
 #[derive(Debug)]
 pub struct Connection<B: Driver> {
     addr: B::Endpoint,
@@ -161,6 +324,13 @@ pub struct Connection<B: Driver> {
     service: Option<B::Service>,
     /// sequence number
     seq_id: AtomicU16,
+    /// reconnect policy and state events
+    recovery: Recovery,
+    timeouts: Timeouts,
+    /// keep CIP connection alive when idle
+    keep_alive: bool,
+    /// last time of connected send
+    last_active: Instant,
 }
 
 impl<B: Driver> Connection<B> {
@@ -173,9 +343,46 @@ impl<B: Driver> Connection<B> {
             connected_options: None,
             service: None,
             seq_id: Default::default(),
+            recovery: Default::default(),
+            timeouts: Default::default(),
+            keep_alive: true,
+            last_active: Instant::now(),
         }
     }
 
+    /// keep CIP connection alive with connected requests on heartbeat when idle, default true;
+    /// heartbeat is sent by [`SharedClient`] or by the caller, see [`KeepAlive`]
+    #[inline]
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// reconnect when the transport, the session or the CIP connection is lost
+    #[inline]
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.recovery.set_policy(Some(policy));
+        self
+    }
+
+    /// watch state changes of the CIP connection
+    #[inline]
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.recovery.subscribe()
+    }
+
+    /// set timeouts
+    #[inline]
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    #[inline]
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
     /// current remote endpoint, driver specific
     #[inline]
     pub fn remote_endpoint(&self) -> &B::Endpoint {
@@ -194,6 +401,34 @@ impl<B: Driver> Connection<B> {
         self.connection_id().is_some()
     }
 
+    /// connection size, the negotiated one if connected;
+    /// it might be smaller than the requested one if Large Forward Open rejected
+    #[inline]
+    pub fn connection_size(&self) -> u16 {
+        self.connected_options
+            .as_ref()
+            .unwrap_or(&self.origin_options)
+            .o_t_params
+            .connection_size
+    }
+
+    /// is CIP connection opened by Large Forward Open?
+    #[inline]
+    pub fn large_open(&self) -> bool {
+        self.connected_options
+            .as_ref()
+            .map(|v| v.large_open)
+            .unwrap_or_default()
+    }
+
+    /// CIP connection timeout: `o_t_rpi * 4 * 2^timeout_multiplier`, none if not connected
+    #[inline]
+    pub fn connection_timeout(&self) -> Option<Duration> {
+        let opts = self.connected_options.as_ref()?;
+        let factor = 4_u64 << opts.timeout_multiplier.min(7);
+        Some(Duration::from_micros(opts.o_t_rpi as u64 * factor))
+    }
+
     /// generate next sequence number
     #[inline]
     fn next_sequence_number(&mut self) -> u16 {
@@ -208,6 +443,26 @@ impl<B: Driver> Connection<B> {
         }
     }
 
+    /// open connection if not already connected;
+    /// retry with a new connection serial number at most `retries` times if duplicate forward open
+    pub async fn open_with_retry(&mut self, retries: u32) -> Result<u32> {
+        let mut retries = retries;
+        loop {
+            match self.open_connection().await {
+                Err(ClientError::ForwardOpen { ref err }) if err.is_duplicate() && retries > 0 => {
+                    retries -= 1;
+                    self.renew_connection_serial_number();
+                }
+                res => return res,
+            }
+        }
+    }
+
+    #[inline]
+    fn renew_connection_serial_number(&mut self) {
+        self.origin_options = self.origin_options.clone().renew_connection_serial_number();
+    }
+
     /// close current connection and open a new connection
     #[inline]
     pub async fn reconnect(&mut self) -> Result<()> {
@@ -218,44 +473,108 @@ impl<B: Driver> Connection<B> {
 
     #[inline]
     async fn ensure_service(&mut self) -> Result<()> {
-        if self.service.is_none() {
-            let service = B::build_service(self.addr.clone()).await?;
-            self.service = Some(service);
-        }
-        match self.service {
-            None => unreachable!(),
-            Some(ref mut service) => {
-                service.open().await?;
+        let res = timeout(self.timeouts.connect_timeout(), "connect", async {
+            if self.service.is_none() {
+                let service = B::build_service(self.addr.clone()).await?;
+                self.service = Some(service);
             }
+            match self.service {
+                None => unreachable!(),
+                Some(ref mut service) => service.open().await,
+            }
+        })
+        .await;
+        if let Err(e) = res {
+            if e.is_timeout() {
+                self.service = None;
+            }
+            return Err(e);
         }
         Ok(())
     }
 
-    /// open connection if not already connected
+    /// open connection if not already connected;
+    /// fall back to Forward Open if Large Forward Open not supported by the target
     #[inline]
     async fn open_connection(&mut self) -> Result<u32> {
         // create service if not created
         self.ensure_service().await?;
-        let service = self.service.as_mut().expect("expected service");
         if self.connected_options.is_none() {
-            let reply = service.forward_open(self.origin_options.clone()).await?;
+            let mut options = self.origin_options.clone();
+            let reply = loop {
+                let service = self.service.as_mut().expect("expected service");
+                let fut = service.forward_open(options.clone());
+                let res = timeout(self.timeouts.forward_open_timeout(), "forward open", fut).await;
+                let rejected = match res {
+                    Ok(ref reply) => {
+                        matches!(reply.value(), Either::Right(_))
+                            && is_large_open_unsupported(reply.status())
+                    }
+                    Err(ref e) => e
+                        .status()
+                        .map(is_large_open_unsupported)
+                        .unwrap_or_default(),
+                };
+                if rejected && options.large_open {
+                    log::debug!("large forward open rejected, fall back to forward open");
+                    options = options.without_large_open();
+                    continue;
+                }
+                match res {
+                    Ok(reply) => break reply,
+                    Err(e) => {
+                        if e.is_timeout() {
+                            self.service = None;
+                        }
+                        return Err(e);
+                    }
+                }
+            };
+            let status = reply.status().clone();
             match reply.into_value() {
                 Either::Left(reply) => {
-                    let opts = self
-                        .origin_options
-                        .clone()
+                    let opts = options
                         .o_t_connection_id(reply.o_t_connection_id)
                         .connection_serial_number(reply.connection_serial_number)
                         .o_t_rpi(reply.o_t_api)
                         .t_o_rpi(reply.t_o_api);
                     self.connected_options = Some(opts);
+                    self.last_active = Instant::now();
+                    self.recovery.set_state(ConnectionState::Connected);
                 }
-                Either::Right(_) => return Err(Error::custom("forward open failed")),
+                Either::Right(fail) => return Err(ForwardOpenError::new(status, fail).into()),
             }
         }
         Ok(self.connection_id().unwrap())
     }
 
+    /// drop what is lost, then reconnect if any policy; true if reconnected
+    async fn recover(&mut self, fault: Fault) -> bool {
+        if fault != Fault::Connection {
+            self.service = None;
+        }
+        if self.connected_options.take().is_some() {
+            // the lost connection might be still known by the target until it times out
+            self.renew_connection_serial_number();
+        }
+        self.recovery.set_state(ConnectionState::Disconnected);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            if !self.recovery.wait(attempt).await {
+                return false;
+            }
+            match self.open_connection().await {
+                Ok(_) => return true,
+                Err(e) => {
+                    if matches!(Fault::of(&e), Some(Fault::Transport | Fault::Session)) {
+                        self.service = None;
+                    }
+                }
+            }
+        }
+    }
+
     /// close connection
     #[inline]
     async fn close_connection(&mut self) -> Result<()> {
@@ -269,7 +588,8 @@ impl<B: Driver> Connection<B> {
                     originator_vendor_id: conn.vendor_id,
                     connection_path: conn.connection_path,
                 };
-                let _ = service.forward_close(request).await;
+                let fut = service.forward_close(request);
+                let _ = timeout(self.timeouts.forward_open_timeout(), "forward close", fut).await;
             }
         }
         Ok(())
@@ -280,12 +600,35 @@ impl<B: Driver> Connection<B> {
 impl<B: Driver> Heartbeat for Connection<B> {
     type Error = ClientError;
 
-    /// send Heartbeat message to keep underline transport alive
+    /// send Get_Attribute_Single of the identity vendor id if the CIP connection idle
+    /// for the keep alive interval, otherwise Heartbeat message to keep underline transport alive
     #[inline]
     async fn heartbeat(&mut self) -> Result<()> {
+        let due = self
+            .keep_alive_interval()
+            .map(|v| self.last_active.elapsed() >= v)
+            .unwrap_or_default();
+        if due {
+            let path = EPath::new()
+                .with_class(0x01)
+                .with_instance(0x01)
+                .with_attribute(0x01);
+            let _: MessageReply<u16> = self.send(MessageRequest::new(0x0E, path, ())).await?;
+            return Ok(());
+        }
         if let Some(ref mut service) = self.service {
-            service.heartbeat().await?;
-            //TODO: is there a way to keep CIP connection alive?
+            let res = timeout(
+                self.timeouts.request_timeout(),
+                "heartbeat",
+                service.heartbeat(),
+            )
+            .await;
+            if let Err(e) = res {
+                if let Some(fault) = Fault::of(&e) {
+                    self.recover(fault).await;
+                }
+                return Err(e);
+            }
         }
         Ok(())
     }
@@ -302,11 +645,62 @@ impl<B: Driver> MessageService for Connection<B> {
         D: Encode + Send + Sync,
         R: MessageReplyInterface + Decode<'de> + 'static,
     {
-        // create connection if not connected
+        let mut replayed = false;
+        loop {
+            // create connection if not connected
+            let cid = self.open_connection().await?;
+            let sid = self.next_sequence_number();
+            let service = self.service.as_mut().expect("expected service");
+            let req = MessageRequest::new(mr.service_code, &mr.path, &mr.data);
+            let fut = service.connected_send(cid, sid, req);
+            let err = match timeout(self.timeouts.request_timeout(), "request", fut).await {
+                Ok(res) => {
+                    self.last_active = Instant::now();
+                    return Ok(res);
+                }
+                Err(e) => e,
+            };
+            let fault = match Fault::of(&err) {
+                Some(fault) => fault,
+                None => return Err(err),
+            };
+            if !self.recover(fault).await || replayed || !self.recovery.replay(mr.service_code) {
+                return Err(err);
+            }
+            replayed = true;
+        }
+    }
+
+    /// connected send, all requests are sent before waiting for replies
+    #[inline]
+    async fn send_all<'de, P, D, R>(
+        &mut self,
+        requests: Vec<MessageRequest<P, D>>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
         let cid = self.open_connection().await?;
-        let sid = self.next_sequence_number();
+        let requests = requests
+            .into_iter()
+            .map(|mr| (self.next_sequence_number(), mr))
+            .collect();
         let service = self.service.as_mut().expect("expected service");
-        service.connected_send(cid, sid, mr).await
+        let fut = service.connected_send_all(cid, requests);
+        match timeout(self.timeouts.request_timeout(), "request", fut).await {
+            Ok(res) => {
+                self.last_active = Instant::now();
+                Ok(res)
+            }
+            Err(e) => {
+                if let Some(fault) = Fault::of(&e) {
+                    self.recover(fault).await;
+                }
+                Err(e)
+            }
+        }
     }
 
     /// close current connection and underline transport
@@ -314,8 +708,9 @@ impl<B: Driver> MessageService for Connection<B> {
     async fn close(&mut self) -> Result<()> {
         let _ = self.close_connection().await;
         if let Some(mut service) = self.service.take() {
-            let _ = service.close().await;
+            let _ = timeout(self.timeouts.request_timeout(), "close", service.close()).await;
         }
+        self.recovery.set_state(ConnectionState::Disconnected);
         Ok(())
     }
 
@@ -326,6 +721,33 @@ impl<B: Driver> MessageService for Connection<B> {
     }
 }
 
+impl<B: Driver> MessageSize for Connection<B> {
+    /// connection size minus the sequence count
+    #[inline]
+    fn max_message_size(&self) -> usize {
+        (self.connection_size() as usize).saturating_sub(2)
+    }
+}
+
+impl<B: Driver> KeepAlive for Connection<B> {
+    /// half of the connection timeout, none if not connected or keep alive disabled
+    #[inline]
+    fn keep_alive_interval(&self) -> Option<Duration> {
+        if self.keep_alive {
+            self.connection_timeout().map(|v| v / 2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Large Forward Open rejected by the target as not supported:
+/// service not supported, or invalid connection size
+#[inline]
+fn is_large_open_unsupported(status: &Status) -> bool {
+    status.general == 0x08 || (status.general == 0x01 && status.extended() == Some(0x0109))
+}
+
 /// client with CIP connection or without CIP connection
 #[derive(Debug)]
 pub struct MaybeConnected<B: Driver>(Either<Client<B>, Connection<B>>);
@@ -343,6 +765,17 @@ impl<B: Driver> DerefMut for MaybeConnected<B> {
     }
 }
 
+impl<B: Driver> MaybeConnected<B> {
+    /// watch state changes of the transport or the CIP connection
+    #[inline]
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        match self.0 {
+            Either::Left(ref c) => c.watch_state(),
+            Either::Right(ref c) => c.watch_state(),
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl<B: Driver> Heartbeat for MaybeConnected<B> {
     type Error = ClientError;
@@ -373,6 +806,23 @@ impl<B: Driver> MessageService for MaybeConnected<B> {
         }
     }
 
+    /// send message requests, all requests are sent before waiting for replies
+    #[inline]
+    async fn send_all<'de, P, D, R>(
+        &mut self,
+        requests: Vec<MessageRequest<P, D>>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
+        match self.0 {
+            Either::Left(ref mut c) => c.send_all(requests).await,
+            Either::Right(ref mut c) => c.send_all(requests).await,
+        }
+    }
+
     /// close underline connection/transport
     #[inline]
     async fn close(&mut self) -> Result<()> {
@@ -391,3 +841,224 @@ impl<B: Driver> MessageService for MaybeConnected<B> {
         }
     }
 }
+
+impl<B: Driver> MessageSize for MaybeConnected<B> {
+    #[inline]
+    fn max_message_size(&self) -> usize {
+        match self.0 {
+            Either::Left(ref c) => c.max_message_size(),
+            Either::Right(ref c) => c.max_message_size(),
+        }
+    }
+}
+
+impl<B: Driver> KeepAlive for MaybeConnected<B> {
+    #[inline]
+    fn keep_alive_interval(&self) -> Option<Duration> {
+        match self.0 {
+            Either::Left(ref c) => c.keep_alive_interval(),
+            Either::Right(ref c) => c.keep_alive_interval(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cip::{connection::MAX_CONNECTION_SIZE, MessageReply},
+        ErrorKind,
+    };
+    use mock::{MockDriver, MockEndpoint, MockTrace};
+    use std::{
+        net::SocketAddrV4,
+        sync::{atomic::Ordering, Arc, Mutex},
+    };
+
+    #[test]
+    fn test_keep_alive_interval() {
+        let addr = SocketAddrV4::new([192, 168, 0, 83].into(), 0xAF12);
+        let mut conn = EipConnection::new(addr, OpenOptions::default());
+        assert_eq!(conn.keep_alive_interval(), None);
+        conn.connected_options = Some(OpenOptions::default().o_t_rpi(10_000).timeout_multiplier(1));
+        assert_eq!(conn.connection_timeout(), Some(Duration::from_millis(80)));
+        assert_eq!(conn.keep_alive_interval(), Some(Duration::from_millis(40)));
+        let conn = conn.with_keep_alive(false);
+        assert_eq!(conn.keep_alive_interval(), None);
+    }
+
+    fn large_open_connection(status: (u8, Option<u16>)) -> Connection<MockDriver> {
+        let endpoint = MockEndpoint {
+            large_open_status: Some(status),
+            ..Default::default()
+        };
+        let options = OpenOptions::default()
+            .large_open(true)
+            .connection_size(4000);
+        Connection::new(endpoint, options)
+    }
+
+    #[tokio::test]
+    async fn test_large_open_fallback() {
+        // service not supported
+        let mut conn = large_open_connection((0x08, None));
+        conn.open_connection().await.unwrap();
+        assert!(!conn.large_open());
+        assert_eq!(conn.connection_size(), MAX_CONNECTION_SIZE);
+
+        // invalid connection size
+        let mut conn = large_open_connection((0x01, Some(0x0109)));
+        conn.open_connection().await.unwrap();
+        assert!(!conn.large_open());
+
+        // other errors not fall back, eg. duplicate forward open
+        let mut conn = large_open_connection((0x01, Some(0x0100)));
+        let err = conn.open_connection().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ForwardOpen);
+        assert_eq!(err.status().unwrap().extended(), Some(0x0100));
+        assert!(!conn.connected());
+
+        let mut conn = large_open_connection((0x05, None));
+        let err = conn.open_connection().await.unwrap_err();
+        assert_eq!(err.status().unwrap().general, 0x05);
+
+        // supported
+        let mut conn = Connection::<MockDriver>::new(
+            MockEndpoint::default(),
+            OpenOptions::default()
+                .large_open(true)
+                .connection_size(4000),
+        );
+        conn.open_connection().await.unwrap();
+        assert!(conn.large_open());
+        assert_eq!(conn.connection_size(), 4000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_request_timeout() {
+        let trace = Arc::new(MockTrace::default());
+        *trace.delay.lock().unwrap() = Some(Duration::from_secs(2));
+        let endpoint = MockEndpoint {
+            trace: Some(trace.clone()),
+            ..Default::default()
+        };
+        let timeouts = Timeouts::default()
+            .request(Duration::from_secs(1))
+            .unconnected_ticks(0x05, 0x10);
+        let mut client = Client::<MockDriver>::new(endpoint).with_timeouts(timeouts);
+
+        // timed out, the transport in unknown state is dropped
+        let start = tokio::time::Instant::now();
+        let res: Result<MessageReply<()>> = client
+            .send(MessageRequest::new(0x4C, EPath::default(), ()))
+            .await;
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::Timeout);
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert!(client.closed());
+
+        // opened again by the next request
+        *trace.delay.lock().unwrap() = None;
+        let _: MessageReply<()> = client
+            .send(MessageRequest::new(0x4C, EPath::default(), ()))
+            .await
+            .unwrap();
+        assert!(!client.closed());
+        assert_eq!(trace.sessions.load(Ordering::SeqCst), 2);
+
+        // priority_ticks | timeout_ticks | ..
+        let sent = trace.unconnected.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        for data in sent.iter() {
+            assert_eq!(data[..2], [0x05, 0x10]);
+        }
+    }
+
+    /// record state changes, except `Disconnected` which is followed by `Reconnecting` at once
+    fn record_states(mut rx: watch::Receiver<ConnectionState>) -> Arc<Mutex<Vec<ConnectionState>>> {
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let res = states.clone();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let state = *rx.borrow_and_update();
+                if state != ConnectionState::Disconnected {
+                    states.lock().unwrap().push(state);
+                }
+            }
+        });
+        res
+    }
+
+    /// let the recorder catch up, then take the states
+    async fn take_states(states: &Mutex<Vec<ConnectionState>>) -> Vec<ConnectionState> {
+        tokio::task::yield_now().await;
+        std::mem::take(&mut *states.lock().unwrap())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect() {
+        let trace = Arc::new(MockTrace::default());
+        let endpoint = MockEndpoint {
+            trace: Some(trace.clone()),
+            ..Default::default()
+        };
+        let policy = ReconnectPolicy::default().max_retries(2).replay_reads(true);
+        let mut client = Client::<MockDriver>::new(endpoint).with_reconnect(policy);
+        let states = record_states(client.watch_state());
+
+        let _: MessageReply<()> = client
+            .send(MessageRequest::new(0x4C, EPath::default(), ()))
+            .await
+            .unwrap();
+        assert_eq!(trace.sessions.load(Ordering::SeqCst), 1);
+        assert_eq!(take_states(&states).await, [ConnectionState::Connected]);
+
+        // transport dropped, read replayed once after the session registered again
+        trace.drops.store(1, Ordering::SeqCst);
+        let _: MessageReply<()> = client
+            .send(MessageRequest::new(0x4C, EPath::default(), ()))
+            .await
+            .unwrap();
+        assert_eq!(trace.sessions.load(Ordering::SeqCst), 2);
+        assert_eq!(*trace.requests.lock().unwrap(), [0x4C, 0x4C, 0x4C]);
+        assert_eq!(
+            take_states(&states).await,
+            [
+                ConnectionState::Reconnecting { attempt: 1 },
+                ConnectionState::Connected
+            ]
+        );
+
+        // write not replayed
+        trace.drops.store(1, Ordering::SeqCst);
+        let res: Result<MessageReply<()>> = client
+            .send(MessageRequest::new(0x4D, EPath::default(), ()))
+            .await;
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::Io);
+        assert_eq!(trace.sessions.load(Ordering::SeqCst), 3);
+        assert_eq!(*trace.requests.lock().unwrap(), [0x4C, 0x4C, 0x4C, 0x4D]);
+        assert_eq!(
+            take_states(&states).await,
+            [
+                ConnectionState::Reconnecting { attempt: 1 },
+                ConnectionState::Connected
+            ]
+        );
+
+        // failed after all attempts
+        trace.drops.store(usize::MAX, Ordering::SeqCst);
+        let res: Result<MessageReply<()>> = client
+            .send(MessageRequest::new(0x4C, EPath::default(), ()))
+            .await;
+        assert!(res.is_err());
+        assert_eq!(trace.sessions.load(Ordering::SeqCst), 3);
+        assert_eq!(trace.requests.lock().unwrap().len(), 5);
+        assert_eq!(
+            take_states(&states).await,
+            [
+                ConnectionState::Reconnecting { attempt: 1 },
+                ConnectionState::Reconnecting { attempt: 2 },
+                ConnectionState::Failed
+            ]
+        );
+    }
+}
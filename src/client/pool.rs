@@ -0,0 +1,603 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::*;
+use core::hash::Hash;
+use std::{
+    collections::HashMap,
+    net::SocketAddrV4,
+    sync::{atomic::Ordering, Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+/// default max number of CIP connections per controller
+pub const DEFAULT_MAX_CONNECTIONS: usize = 8;
+
+/// identify pooled clients
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey<E = SocketAddrV4> {
+    /// remote endpoint, driver specific
+    pub endpoint: E,
+    /// connection path, see [`Client::with_connection_path`] and [`OpenOptions::connection_path`]
+    pub path: EPath,
+    /// with CIP connection or without CIP connection
+    pub connected: bool,
+}
+
+impl<E> PoolKey<E> {
+    /// key of [`Client`]
+    #[inline]
+    pub fn unconnected(endpoint: E, path: impl Into<EPath>) -> Self {
+        Self {
+            endpoint,
+            path: path.into(),
+            connected: false,
+        }
+    }
+
+    /// key of [`Connection`]
+    #[inline]
+    pub fn connected(endpoint: E, path: impl Into<EPath>) -> Self {
+        Self {
+            endpoint,
+            path: path.into(),
+            connected: true,
+        }
+    }
+}
+
+/// options of [`Pool`]
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    max_connections: usize,
+    idle_timeout: Duration,
+    health_check_interval: Duration,
+    open_options: OpenOptions,
+    reconnect: Option<ReconnectPolicy>,
+    timeouts: Timeouts,
+}
+
+impl Default for PoolOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            idle_timeout: Duration::from_secs(60),
+            health_check_interval: Duration::from_secs(30),
+            open_options: Default::default(),
+            reconnect: None,
+            timeouts: Default::default(),
+        }
+    }
+}
+
+impl PoolOptions {
+    /// max number of CIP connections per controller, default 8, at least 1;
+    /// clients without CIP connection are not limited
+    #[inline]
+    pub fn max_connections(mut self, val: usize) -> Self {
+        self.max_connections = val.max(1);
+        self
+    }
+
+    /// idle clients are closed after the timeout, default 60 seconds
+    #[inline]
+    pub fn idle_timeout(mut self, val: Duration) -> Self {
+        self.idle_timeout = val;
+        self
+    }
+
+    /// idle clients are checked with heartbeat before reuse
+    /// if not used within the interval, default 30 seconds
+    #[inline]
+    pub fn health_check_interval(mut self, val: Duration) -> Self {
+        self.health_check_interval = val;
+        self
+    }
+
+    /// template of [`Connection`] options, the connection path is replaced by the one of [`PoolKey`];
+    /// each connection gets its own connection serial number
+    #[inline]
+    pub fn open_options(mut self, val: OpenOptions) -> Self {
+        self.open_options = val;
+        self
+    }
+
+    /// reconnect policy of pooled clients, none by default
+    #[inline]
+    pub fn reconnect(mut self, val: ReconnectPolicy) -> Self {
+        self.reconnect = Some(val);
+        self
+    }
+
+    /// timeouts of pooled clients
+    #[inline]
+    pub fn timeouts(mut self, val: Timeouts) -> Self {
+        self.timeouts = val;
+        self
+    }
+}
+
+/// pool of clients and connections across controllers, shared by clones;
+/// clients are built on demand, and returned to the pool when [`Pooled`] dropped
+///
+/// ```rust,ignore
+/// let pool = AbEipPool::new(PoolOptions::default().max_connections(2));
+/// let key = PoolKey::connected(addr, OpenOptions::default().connection_path);
+/// let mut client = pool.get(key).await?;
+/// let value: TagValue<i32> = client.read_tag(EPath::parse_tag("test_car1_x")?).await?;
+/// drop(client);
+///
+/// // call it periodically to close idle clients and check health
+/// pool.maintain().await;
+/// ```
+pub struct Pool<B: Driver> {
+    inner: Arc<Inner<B>>,
+}
+
+struct Inner<B: Driver> {
+    options: PoolOptions,
+    serial_number: AtomicU16,
+    state: Mutex<State<B>>,
+}
+
+struct State<B: Driver> {
+    closed: bool,
+    idle: HashMap<PoolKey<B::Endpoint>, Vec<Idle<B>>>,
+    /// CIP connection limits by controller
+    limits: HashMap<B::Endpoint, Arc<Semaphore>>,
+}
+
+struct Idle<B: Driver> {
+    client: MaybeConnected<B>,
+    permit: Option<OwnedSemaphorePermit>,
+    since: Instant,
+}
+
+impl<B: Driver> Clone for Pool<B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<B: Driver> fmt::Debug for Pool<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("options", &self.inner.options)
+            .finish()
+    }
+}
+
+impl<B> Pool<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    #[inline]
+    pub fn new(options: PoolOptions) -> Self {
+        let serial_number = AtomicU16::new(options.open_options.connection_serial_number);
+        Self {
+            inner: Arc::new(Inner {
+                options,
+                serial_number,
+                state: Mutex::new(State {
+                    closed: false,
+                    idle: Default::default(),
+                    limits: Default::default(),
+                }),
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn options(&self) -> &PoolOptions {
+        &self.inner.options
+    }
+
+    /// number of idle clients
+    #[inline]
+    pub fn idle_count(&self) -> usize {
+        self.inner.state().idle.values().map(|v| v.len()).sum()
+    }
+
+    /// reuse an idle client if any, otherwise build a new one;
+    /// waits if max number of CIP connections reached and none of them idle;
+    /// fails if the pool closed
+    pub async fn get(&self, key: PoolKey<B::Endpoint>) -> Result<Pooled<B>> {
+        let (expired, idle) = {
+            let mut state = self.inner.state();
+            if state.closed {
+                return Err(Error::custom("pool - closed"));
+            }
+            let expired = state.take_expired(self.inner.options.idle_timeout);
+            let idle = state.idle.get_mut(&key).and_then(|v| v.pop());
+            (expired, idle)
+        };
+        close_all(expired).await;
+
+        if let Some(mut idle) = idle {
+            if idle.since.elapsed() < self.inner.options.health_check_interval
+                || idle.client.heartbeat().await.is_ok()
+            {
+                return Ok(self.pooled(key, idle.client, idle.permit));
+            }
+            let _ = idle.client.close().await;
+        }
+
+        let reconnect = self.inner.options.reconnect.clone();
+        let (client, permit) = if key.connected {
+            let permit = self.acquire(&key.endpoint).await?;
+            let serial_number = self.next_serial_number();
+            let options = self
+                .inner
+                .options
+                .open_options
+                .clone()
+                .connection_path(key.path.clone())
+                .connection_serial_number(serial_number);
+            let mut conn = Connection::new(key.endpoint.clone(), options)
+                .with_timeouts(self.inner.options.timeouts);
+            conn.recovery.set_policy(reconnect);
+            (MaybeConnected(Either::Right(conn)), Some(permit))
+        } else {
+            let mut client = Client::new(key.endpoint.clone())
+                .with_connection_path(key.path.clone())
+                .with_timeouts(self.inner.options.timeouts);
+            client.recovery.set_policy(reconnect);
+            (MaybeConnected(Either::Left(client)), None)
+        };
+        Ok(self.pooled(key, client, permit))
+    }
+
+    /// close idle clients that timed out, then check the others with heartbeat if due;
+    /// call it periodically
+    pub async fn maintain(&self) {
+        let interval = self.inner.options.health_check_interval;
+        let (expired, due) = {
+            let mut state = self.inner.state();
+            let expired = state.take_expired(self.inner.options.idle_timeout);
+            let mut due = Vec::new();
+            for (key, items) in state.idle.iter_mut() {
+                let mut i = 0;
+                while i < items.len() {
+                    if items[i].since.elapsed() >= interval {
+                        due.push((key.clone(), items.remove(i)));
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            (expired, due)
+        };
+        close_all(expired).await;
+        for (key, mut idle) in due {
+            if idle.client.heartbeat().await.is_ok() {
+                idle.since = Instant::now();
+                self.inner.state().push(key, idle);
+            } else {
+                let _ = idle.client.close().await;
+            }
+        }
+    }
+
+    /// close all idle clients; the pool is not usable any more,
+    /// pending and later [`Pool::get`] fail, clients in use are not returned to the pool
+    pub async fn close(&self) {
+        let items: Vec<_> = {
+            let mut state = self.inner.state();
+            state.closed = true;
+            for (_, semaphore) in state.limits.drain() {
+                semaphore.close();
+            }
+            state.idle.drain().flat_map(|(_, v)| v).collect()
+        };
+        close_all(items).await;
+    }
+
+    #[inline]
+    fn pooled(
+        &self,
+        key: PoolKey<B::Endpoint>,
+        client: MaybeConnected<B>,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Pooled<B> {
+        Pooled {
+            pool: self.inner.clone(),
+            key,
+            client: Some(client),
+            permit,
+        }
+    }
+
+    /// acquire a CIP connection of the controller; the least recently used idle connection
+    /// is closed if max number of connections reached
+    async fn acquire(&self, endpoint: &B::Endpoint) -> Result<OwnedSemaphorePermit> {
+        let semaphore = {
+            let mut state = self.inner.state();
+            let max = self.inner.options.max_connections;
+            state
+                .limits
+                .entry(endpoint.clone())
+                .or_insert_with(|| Arc::new(Semaphore::new(max)))
+                .clone()
+        };
+        loop {
+            if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+                return Ok(permit);
+            }
+            let lru = self.inner.state().take_lru_connection(endpoint);
+            match lru {
+                Some(mut idle) => {
+                    let _ = idle.client.close().await;
+                }
+                None => {
+                    return semaphore
+                        .acquire_owned()
+                        .await
+                        .map_err(|_| Error::custom("pool - closed"));
+                }
+            }
+        }
+    }
+
+    /// connection serial number, cannot be 0
+    #[inline]
+    fn next_serial_number(&self) -> u16 {
+        loop {
+            let v = self.inner.serial_number.fetch_add(1, Ordering::Relaxed);
+            if v > 0 {
+                return v;
+            }
+        }
+    }
+}
+
+impl<B: Driver> Inner<B> {
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, State<B>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<B> State<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    #[inline]
+    fn push(&mut self, key: PoolKey<B::Endpoint>, idle: Idle<B>) {
+        self.idle.entry(key).or_default().push(idle);
+    }
+
+    /// idle clients that timed out
+    fn take_expired(&mut self, timeout: Duration) -> Vec<Idle<B>> {
+        let mut res = Vec::new();
+        for items in self.idle.values_mut() {
+            let mut i = 0;
+            while i < items.len() {
+                if items[i].since.elapsed() >= timeout {
+                    res.push(items.remove(i));
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        self.idle.retain(|_, v| !v.is_empty());
+        res
+    }
+
+    /// least recently used idle CIP connection of the controller
+    fn take_lru_connection(&mut self, endpoint: &B::Endpoint) -> Option<Idle<B>> {
+        let (key, idx) = self
+            .idle
+            .iter()
+            .filter(|(k, _)| k.connected && &k.endpoint == endpoint)
+            .flat_map(|(k, v)| {
+                v.iter()
+                    .enumerate()
+                    .map(move |(i, item)| (k, i, item.since))
+            })
+            .min_by_key(|(_, _, since)| *since)
+            .map(|(k, i, _)| (k.clone(), i))?;
+        let items = self.idle.get_mut(&key)?;
+        let res = items.remove(idx);
+        if items.is_empty() {
+            self.idle.remove(&key);
+        }
+        Some(res)
+    }
+}
+
+#[inline]
+async fn close_all<B: Driver>(items: Vec<Idle<B>>) {
+    for mut item in items {
+        let _ = item.client.close().await;
+    }
+}
+
+/// client checked out from [`Pool`], returned to the pool when dropped
+pub struct Pooled<B: Driver>
+where
+    B::Endpoint: Hash + Eq,
+{
+    pool: Arc<Inner<B>>,
+    key: PoolKey<B::Endpoint>,
+    client: Option<MaybeConnected<B>>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<B> Pooled<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    #[inline]
+    pub fn key(&self) -> &PoolKey<B::Endpoint> {
+        &self.key
+    }
+
+    /// close the client instead of returning it to the pool, eg. after transport errors
+    pub async fn discard(mut self) {
+        if let Some(mut client) = self.client.take() {
+            let _ = client.close().await;
+        }
+    }
+}
+
+impl<B> fmt::Debug for Pooled<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pooled").field("key", &self.key).finish()
+    }
+}
+
+impl<B> Deref for Pooled<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    type Target = MaybeConnected<B>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().expect("expected client")
+    }
+}
+
+impl<B> DerefMut for Pooled<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client.as_mut().expect("expected client")
+    }
+}
+
+impl<B> Drop for Pooled<B>
+where
+    B: Driver,
+    B::Endpoint: Hash + Eq,
+{
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            let mut state = self.pool.state();
+            if state.closed {
+                return;
+            }
+            let idle = Idle {
+                client,
+                permit: self.permit.take(),
+                since: Instant::now(),
+            };
+            state.push(self.key.clone(), idle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{MockDriver, MockEndpoint};
+
+    #[tokio::test]
+    async fn test_pool() {
+        let pool = Pool::<EipDriver>::new(PoolOptions::default().max_connections(1));
+        let addr = SocketAddrV4::new([192, 168, 0, 83].into(), 0xAF12);
+        let key = PoolKey::unconnected(addr, EPath::default().with_port_slot(1, 0));
+        let client = pool.get(key.clone()).await.unwrap();
+        assert!(matches!(**client, Either::Left(_)));
+        drop(client);
+        assert_eq!(pool.idle_count(), 1);
+        let client = pool.get(key.clone()).await.unwrap();
+        assert_eq!(pool.idle_count(), 0);
+        client.discard().await;
+        assert_eq!(pool.idle_count(), 0);
+
+        // least recently used idle connection closed when max connections reached
+        let a = PoolKey::connected(addr, EPath::default().with_port_slot(1, 0));
+        let b = PoolKey::connected(addr, EPath::default().with_port_slot(1, 1));
+        drop(pool.get(a.clone()).await.unwrap());
+        drop(pool.get(key).await.unwrap());
+        assert_eq!(pool.idle_count(), 2);
+        let conn = pool.get(b).await.unwrap();
+        assert!(matches!(**conn, Either::Right(_)));
+        assert_eq!(pool.idle_count(), 1);
+        let pending = tokio::time::timeout(Duration::from_millis(10), pool.get(a)).await;
+        assert!(pending.is_err());
+        drop(conn);
+
+        let pool = Pool::<EipDriver>::new(PoolOptions::default().idle_timeout(Duration::ZERO));
+        drop(
+            pool.get(PoolKey::unconnected(addr, EPath::default()))
+                .await
+                .unwrap(),
+        );
+        pool.maintain().await;
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pool_health_check() {
+        let pool =
+            Pool::<MockDriver>::new(PoolOptions::default().health_check_interval(Duration::ZERO));
+        for healthy in [true, false] {
+            let endpoint = MockEndpoint {
+                healthy,
+                ..Default::default()
+            };
+            let mut client = pool
+                .get(PoolKey::unconnected(endpoint, EPath::default()))
+                .await
+                .unwrap();
+            let mr = MessageRequest::new(0x0E, EPath::default(), ());
+            let _: MessageReply<()> = client.send(mr).await.unwrap();
+        }
+        assert_eq!(pool.idle_count(), 2);
+        // unhealthy client closed
+        pool.maintain().await;
+        assert_eq!(pool.idle_count(), 1);
+        let client = pool
+            .get(PoolKey::unconnected(
+                MockEndpoint::default(),
+                EPath::default(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(pool.idle_count(), 0);
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_pool_close() {
+        let pool = Pool::<MockDriver>::new(PoolOptions::default().max_connections(0));
+        assert_eq!(pool.options().max_connections, 1);
+        let key = PoolKey::connected(MockEndpoint::default(), EPath::default());
+        let conn = pool.get(key.clone()).await.unwrap();
+        let pending = {
+            let pool = pool.clone();
+            let key = key.clone();
+            tokio::spawn(async move { pool.get(key).await.map(|_| ()) })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!pending.is_finished());
+        pool.close().await;
+        assert!(pending.await.unwrap().is_err());
+        assert!(pool.get(key).await.is_err());
+        drop(conn);
+        assert_eq!(pool.idle_count(), 0);
+    }
+}
@@ -0,0 +1,237 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use crate::ClientError;
+use core::time::Duration;
+use tokio::{sync::watch, time::sleep};
+
+/// reconnect with exponential backoff when the transport, the session or the CIP connection is lost
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    replay_reads: bool,
+}
+
+impl Default for ReconnectPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            replay_reads: false,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// max number of attempts to reconnect, default 3
+    #[inline]
+    pub fn max_retries(mut self, val: u32) -> Self {
+        self.max_retries = val;
+        self
+    }
+
+    /// wait before the first attempt, doubled for each next attempt; default 100 milliseconds
+    #[inline]
+    pub fn initial_backoff(mut self, val: Duration) -> Self {
+        self.initial_backoff = val;
+        self
+    }
+
+    /// max wait before an attempt, default 5 seconds
+    #[inline]
+    pub fn max_backoff(mut self, val: Duration) -> Self {
+        self.max_backoff = val;
+        self
+    }
+
+    /// send the failed request again after reconnected if it only reads data,
+    /// eg. Read Tag, Get Attribute Single; default false
+    #[inline]
+    pub fn replay_reads(mut self, val: bool) -> Self {
+        self.replay_reads = val;
+        self
+    }
+
+    /// wait before the attempt, starts from 1
+    #[inline]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1_u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// state of the transport and the CIP connection, see `watch_state` of clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// not opened yet, or closed
+    Disconnected,
+    /// ready for requests
+    Connected,
+    /// lost, attempting to reconnect, the attempt starts from 1
+    Reconnecting { attempt: u32 },
+    /// failed to reconnect after all attempts; it will be opened again by later requests
+    Failed,
+}
+
+/// what is lost
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fault {
+    /// underline transport, eg. TCP stream
+    Transport,
+    /// EIP session
+    Session,
+    /// CIP connection
+    Connection,
+}
+
+impl Fault {
+    /// tell the fault from the error
+    #[inline]
+    pub(crate) fn of(err: &ClientError) -> Option<Self> {
+        match err {
+            // the transport is in unknown state if timed out
+            ClientError::Io { .. } | ClientError::Timeout { .. } => Some(Self::Transport),
+            // invalid session handle
+            ClientError::Encapsulation { code: 0x64, .. } => Some(Self::Session),
+            ClientError::Cip { status } if status.is_connection_error() => Some(Self::Connection),
+            _ => None,
+        }
+    }
+
+    /// tell the fault from the error of unconnected requests;
+    /// CIP connection errors are routed statuses, reconnecting the transport does not fix them
+    #[inline]
+    pub(crate) fn of_unconnected(err: &ClientError) -> Option<Self> {
+        Self::of(err).filter(|v| *v != Self::Connection)
+    }
+}
+
+/// reconnect policy and state events of a client
+#[derive(Debug)]
+pub(crate) struct Recovery {
+    policy: Option<ReconnectPolicy>,
+    state: watch::Sender<ConnectionState>,
+}
+
+impl Default for Recovery {
+    #[inline]
+    fn default() -> Self {
+        let (state, _) = watch::channel(ConnectionState::Disconnected);
+        Self {
+            policy: None,
+            state,
+        }
+    }
+}
+
+impl Recovery {
+    #[inline]
+    pub(crate) fn set_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.policy = policy;
+    }
+
+    #[inline]
+    pub(crate) fn subscribe(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    /// notify watchers if changed
+    #[inline]
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        self.state.send_if_modified(|v| {
+            if *v != state {
+                *v = state;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    /// send the request again after reconnected?
+    #[inline]
+    pub(crate) fn replay(&self, service_code: u8) -> bool {
+        match self.policy {
+            Some(ref policy) => policy.replay_reads && is_read_service(service_code),
+            None => false,
+        }
+    }
+
+    /// wait before the attempt; false if no policy or all attempts failed
+    pub(crate) async fn wait(&self, attempt: u32) -> bool {
+        match self.policy {
+            Some(ref policy) if attempt <= policy.max_retries => {
+                self.set_state(ConnectionState::Reconnecting { attempt });
+                sleep(policy.backoff(attempt)).await;
+                true
+            }
+            Some(_) => {
+                self.set_state(ConnectionState::Failed);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// services that only read data, safe to send again
+#[inline]
+fn is_read_service(service_code: u8) -> bool {
+    match service_code {
+        0x01 // Get Attributes All
+        | 0x03 // Get Attribute List
+        | 0x0E // Get Attribute Single
+        | 0x4C // Read Tag, Read Template
+        | 0x52 // Read Tag Fragmented
+        | 0x55 // Get Instance Attribute List
+        => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rseip_core::Error;
+    use std::io;
+
+    #[test]
+    fn test_backoff() {
+        let policy = ReconnectPolicy::default()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1));
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(100), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_fault() {
+        let err = ClientError::from(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert_eq!(Fault::of(&err), Some(Fault::Transport));
+        let err = ClientError::encapsulation_status(0x64, "x");
+        assert_eq!(Fault::of(&err), Some(Fault::Session));
+        let status = rseip_cip::Status::new(1).with_additional(0x0107);
+        let err: ClientError = rseip_cip::error::cip_error_status(status);
+        assert_eq!(Fault::of(&err), Some(Fault::Connection));
+        assert_eq!(Fault::of_unconnected(&err), None);
+        // duplicate forward open
+        let status = rseip_cip::Status::new(1).with_additional(0x0100);
+        let err: ClientError = rseip_cip::error::cip_error_status(status);
+        assert_eq!(Fault::of(&err), None);
+        assert_eq!(Fault::of(&ClientError::custom("x")), None);
+    }
+}
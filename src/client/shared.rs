@@ -0,0 +1,415 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use super::*;
+use bytes::Buf;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use rseip_cip::codec::decode::message_reply::decode_service_and_status;
+use rseip_core::codec::{Decoder, LittleEndianDecoder, LittleEndianEncoder};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+/// default number of requests queued before callers have to wait
+pub const DEFAULT_QUEUE_SIZE: usize = 32;
+
+type Responder<T> = oneshot::Sender<Result<T>>;
+
+enum Command {
+    Send(MessageRequest<Bytes, Bytes>, Responder<RawReply>),
+    SendAll(
+        Vec<MessageRequest<Bytes, Bytes>>,
+        Responder<Vec<Result<RawReply>>>,
+    ),
+    Heartbeat(Responder<()>),
+    Close(Responder<()>),
+}
+
+/// state of the underline service, updated by the actor task
+#[derive(Debug)]
+struct State {
+    closed: AtomicBool,
+    max_message_size: AtomicUsize,
+}
+
+/// cloneable client handle, requests of all clones are sent over one session
+/// by an actor task that owns the underline client or connection;
+/// at most `queue_size` requests are queued, callers wait if the queue is full.
+///
+/// the actor task sends heartbeat if idle for the [`KeepAlive`] interval of the underline service,
+/// and stops when all handles are dropped.
+///
+/// queued requests are sent one by one, so requests of different callers are not pipelined;
+/// only the requests of a single [`MessageService::send_all`] are sent before waiting for replies
+///
+/// ```rust,ignore
+/// let client = AbEipConnection::new_host_lookup("192.168.0.83", OpenOptions::default()).await?;
+/// let client = SharedClient::new(client);
+/// for _ in 0..10 {
+///     let mut client = client.clone();
+///     tokio::spawn(async move {
+///         let value: TagValue<i32> = client.read_tag(EPath::parse_tag("test_car1_x")?).await?;
+///         Ok::<_, ClientError>(value)
+///     });
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedClient {
+    tx: mpsc::Sender<Command>,
+    state: Arc<State>,
+}
+
+impl SharedClient {
+    /// spawn the actor task with [`DEFAULT_QUEUE_SIZE`], must be called within a tokio runtime
+    #[inline]
+    pub fn new<S>(service: S) -> Self
+    where
+        S: MessageService<Error = ClientError>
+            + Heartbeat<Error = ClientError>
+            + MessageSize
+            + KeepAlive
+            + 'static,
+    {
+        Self::with_queue_size(service, DEFAULT_QUEUE_SIZE)
+    }
+
+    /// spawn the actor task, must be called within a tokio runtime
+    pub fn with_queue_size<S>(service: S, queue_size: usize) -> Self
+    where
+        S: MessageService<Error = ClientError>
+            + Heartbeat<Error = ClientError>
+            + MessageSize
+            + KeepAlive
+            + 'static,
+    {
+        let (tx, rx) = mpsc::channel(queue_size.max(1));
+        let state = Arc::new(State {
+            closed: AtomicBool::new(service.closed()),
+            max_message_size: AtomicUsize::new(service.max_message_size()),
+        });
+        tokio::spawn(run(service, rx, state.clone()));
+        Self { tx, state }
+    }
+
+    /// queue the command, then wait for the result
+    async fn call<T>(&self, f: impl FnOnce(Responder<T>) -> Command) -> Result<T> {
+        let (tx, rx) = oneshot::channel();
+        self.tx.send(f(tx)).await.map_err(|_| stopped_error())?;
+        rx.await.map_err(|_| stopped_error())?
+    }
+}
+
+#[inline]
+fn encode_request<P: Encode, D: Encode>(
+    mr: MessageRequest<P, D>,
+) -> Result<MessageRequest<Bytes, Bytes>> {
+    let mut encoder = LittleEndianEncoder::<ClientError>::new();
+    Ok(MessageRequest::new(
+        mr.service_code,
+        encoder.encode_to_bytes(mr.path)?,
+        encoder.encode_to_bytes(mr.data)?,
+    ))
+}
+
+#[inline]
+fn decode_reply<'de, R: Decode<'de>>(reply: RawReply) -> Result<R> {
+    let mut decoder = LittleEndianDecoder::<ClientError>::new(reply.data);
+    decoder.decode_any()
+}
+
+#[inline]
+fn stopped_error() -> ClientError {
+    ClientError::custom("shared client - actor task stopped")
+}
+
+/// actor task, handles commands one by one; a `SendAll` is pipelined as a whole
+async fn run<S>(mut service: S, mut rx: mpsc::Receiver<Command>, state: Arc<State>)
+where
+    S: MessageService<Error = ClientError>
+        + Heartbeat<Error = ClientError>
+        + MessageSize
+        + KeepAlive,
+{
+    loop {
+        let cmd = match service.keep_alive_interval() {
+            Some(interval) => match tokio::time::timeout(interval, rx.recv()).await {
+                Ok(cmd) => cmd,
+                Err(_) => {
+                    if let Err(e) = service.heartbeat().await {
+                        log::warn!("shared client - keep alive failed: {}", e);
+                    }
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        let cmd = match cmd {
+            Some(cmd) => cmd,
+            None => break,
+        };
+        match cmd {
+            Command::Send(mr, tx) => {
+                let res = service.send(mr).await;
+                let _ = tx.send(res);
+            }
+            Command::SendAll(requests, tx) => {
+                let res = service.send_all(requests).await;
+                let _ = tx.send(res);
+            }
+            Command::Heartbeat(tx) => {
+                let res = service.heartbeat().await;
+                let _ = tx.send(res);
+            }
+            Command::Close(tx) => {
+                let res = service.close().await;
+                let _ = tx.send(res);
+            }
+        }
+        state.closed.store(service.closed(), Ordering::Relaxed);
+        state
+            .max_message_size
+            .store(service.max_message_size(), Ordering::Relaxed);
+    }
+    let _ = service.close().await;
+}
+
+#[async_trait::async_trait]
+impl Heartbeat for SharedClient {
+    type Error = ClientError;
+    /// send Heartbeat message to keep underline transport alive
+    #[inline]
+    async fn heartbeat(&mut self) -> Result<()> {
+        self.call(Command::Heartbeat).await
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageService for SharedClient {
+    type Error = ClientError;
+
+    /// encode the request, queue it, then decode the reply when received
+    #[inline]
+    async fn send<'de, P, D, R>(&mut self, mr: MessageRequest<P, D>) -> Result<R>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + 'static,
+    {
+        let mr = encode_request(mr)?;
+        let reply = self.call(|tx| Command::Send(mr, tx)).await?;
+        decode_reply(reply)
+    }
+
+    /// encode the requests, queue them as a whole, then decode the replies when received
+    #[inline]
+    async fn send_all<'de, P, D, R>(
+        &mut self,
+        requests: Vec<MessageRequest<P, D>>,
+    ) -> Result<Vec<Result<R>>>
+    where
+        P: Encode + Send + Sync,
+        D: Encode + Send + Sync,
+        R: MessageReplyInterface + Decode<'de> + Send + 'static,
+    {
+        let requests = requests
+            .into_iter()
+            .map(encode_request)
+            .collect::<Result<Vec<_>>>()?;
+        let replies = self.call(|tx| Command::SendAll(requests, tx)).await?;
+        Ok(replies
+            .into_iter()
+            .map(|reply| reply.and_then(decode_reply))
+            .collect())
+    }
+
+    /// close underline transport, it will be opened again by later requests
+    #[inline]
+    async fn close(&mut self) -> Result<()> {
+        self.call(Command::Close).await
+    }
+
+    /// is underline transport closed?
+    #[inline]
+    fn closed(&self) -> bool {
+        self.state.closed.load(Ordering::Relaxed)
+    }
+}
+
+impl MessageSize for SharedClient {
+    #[inline]
+    fn max_message_size(&self) -> usize {
+        self.state.max_message_size.load(Ordering::Relaxed)
+    }
+}
+
+/// undecoded message reply, passed from the actor task to the caller
+struct RawReply {
+    reply_service: u8,
+    status: Status,
+    /// the whole reply, service code and status included
+    data: Bytes,
+}
+
+impl MessageReplyInterface for RawReply {
+    type Value = Bytes;
+
+    #[inline]
+    fn reply_service(&self) -> u8 {
+        self.reply_service
+    }
+
+    #[inline]
+    fn status(&self) -> &Status {
+        &self.status
+    }
+
+    #[inline]
+    fn value(&self) -> &Self::Value {
+        &self.data
+    }
+
+    #[inline]
+    fn into_value(self) -> Self::Value {
+        self.data
+    }
+}
+
+impl<'de> Decode<'de> for RawReply {
+    #[inline]
+    fn decode<D>(mut decoder: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Decoder<'de>,
+    {
+        let size = decoder.remaining();
+        let data = decoder.buf_mut().copy_to_bytes(size);
+        let (reply_service, status) =
+            decode_service_and_status(LittleEndianDecoder::<D::Error>::new(data.clone()))?;
+        Ok(Self {
+            reply_service,
+            status,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// replies the request data with service code and success status
+    #[derive(Default)]
+    struct EchoService {
+        keep_alive: Option<Duration>,
+        heartbeats: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl MessageService for EchoService {
+        type Error = ClientError;
+
+        async fn send<'de, P, D, R>(&mut self, mr: MessageRequest<P, D>) -> Result<R>
+        where
+            P: Encode + Send + Sync,
+            D: Encode + Send + Sync,
+            R: MessageReplyInterface + Decode<'de> + 'static,
+        {
+            let data = LittleEndianEncoder::<ClientError>::new().encode_to_bytes(mr.data)?;
+            let mut buf = bytes::BytesMut::new();
+            buf.extend_from_slice(&[mr.service_code | 0x80, 0, 0, 0]);
+            buf.extend_from_slice(&data);
+            LittleEndianDecoder::<ClientError>::new(buf.freeze()).decode_any()
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn closed(&self) -> bool {
+            false
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Heartbeat for EchoService {
+        type Error = ClientError;
+        async fn heartbeat(&mut self) -> Result<()> {
+            self.heartbeats.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    impl MessageSize for EchoService {
+        fn max_message_size(&self) -> usize {
+            100
+        }
+    }
+
+    impl KeepAlive for EchoService {
+        fn keep_alive_interval(&self) -> Option<Duration> {
+            self.keep_alive
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_send() {
+        let client = SharedClient::with_queue_size(EchoService::default(), 2);
+        assert_eq!(client.max_message_size(), 100);
+        let tasks: Vec<_> = (0..10_u16)
+            .map(|i| {
+                let mut client = client.clone();
+                tokio::spawn(async move {
+                    let mr = MessageRequest::new(0x4C, (), i);
+                    let reply: MessageReply<u16> = client.send(mr).await.unwrap();
+                    assert_eq!(reply.reply_service, 0xCC);
+                    assert_eq!(reply.data, i);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let mut client = client.clone();
+        let requests = (0..3_u16)
+            .map(|i| MessageRequest::new(0x4D, (), i))
+            .collect();
+        let replies: Vec<Result<MessageReply<u16>>> = client.send_all(requests).await.unwrap();
+        for (i, reply) in replies.into_iter().enumerate() {
+            assert_eq!(reply.unwrap().data, i as u16);
+        }
+    }
+
+    /// move the paused clock, then let the actor task run
+    async fn advance(d: Duration) {
+        tokio::time::advance(d).await;
+        tokio::task::yield_now().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shared_keep_alive() {
+        let heartbeats = Arc::new(AtomicUsize::new(0));
+        let service = EchoService {
+            keep_alive: Some(Duration::from_millis(10)),
+            heartbeats: heartbeats.clone(),
+        };
+        let interval = Duration::from_millis(10);
+        let mut client = SharedClient::new(service);
+        tokio::task::yield_now().await;
+        for i in 1..=3 {
+            advance(interval).await;
+            assert_eq!(heartbeats.load(Ordering::Relaxed), i);
+        }
+
+        // no heartbeat while busy, the interval restarts after the last request
+        advance(interval / 2).await;
+        for i in 0..10_u16 {
+            let _: MessageReply<u16> = client.send(MessageRequest::new(0x4C, (), i)).await.unwrap();
+        }
+        advance(interval / 2).await;
+        assert_eq!(heartbeats.load(Ordering::Relaxed), 3);
+        advance(interval / 2).await;
+        assert_eq!(heartbeats.load(Ordering::Relaxed), 4);
+    }
+}
@@ -0,0 +1,102 @@
+// rseip
+//
+// rseip - Ethernet/IP (CIP) in pure Rust.
+// Copyright: 2021, Joylei <leingliu@gmail.com>
+// License: MIT
+
+use crate::{ClientError, Result};
+use core::{future::Future, time::Duration};
+use rseip_core::Error;
+
+/// timeouts of clients and connections;
+/// a timeout is handled as transport failure, see [`ReconnectPolicy`](super::ReconnectPolicy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    connect: Duration,
+    request: Duration,
+    forward_open: Duration,
+    priority_ticks: u8,
+    timeout_ticks: u8,
+}
+
+impl Default for Timeouts {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(5),
+            request: Duration::from_secs(5),
+            forward_open: Duration::from_secs(5),
+            priority_ticks: 0x03,
+            timeout_ticks: 0xFA,
+        }
+    }
+}
+
+impl Timeouts {
+    /// connect to the target and register session, default 5 seconds
+    #[inline]
+    pub fn connect(mut self, val: Duration) -> Self {
+        self.connect = val;
+        self
+    }
+
+    /// send a request and wait for the reply, default 5 seconds
+    #[inline]
+    pub fn request(mut self, val: Duration) -> Self {
+        self.request = val;
+        self
+    }
+
+    /// Forward Open and Forward Close, default 5 seconds
+    #[inline]
+    pub fn forward_open(mut self, val: Duration) -> Self {
+        self.forward_open = val;
+        self
+    }
+
+    /// timeout of Unconnected Send at the target, `2^priority_ticks * timeout_ticks` milliseconds;
+    /// default `0x03` and `0xFA`, 2 seconds
+    #[inline]
+    pub fn unconnected_ticks(mut self, priority_ticks: u8, timeout_ticks: u8) -> Self {
+        self.priority_ticks = priority_ticks & 0xF;
+        self.timeout_ticks = timeout_ticks;
+        self
+    }
+
+    #[inline]
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect
+    }
+
+    #[inline]
+    pub fn request_timeout(&self) -> Duration {
+        self.request
+    }
+
+    #[inline]
+    pub fn forward_open_timeout(&self) -> Duration {
+        self.forward_open
+    }
+
+    /// `(priority_ticks, timeout_ticks)` of Unconnected Send
+    #[inline]
+    pub fn ticks(&self) -> (u8, u8) {
+        (self.priority_ticks, self.timeout_ticks)
+    }
+}
+
+/// fail with timeout error if the future not completed in time
+#[inline]
+pub(crate) async fn timeout<T>(
+    dur: Duration,
+    op: &'static str,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match tokio::time::timeout(dur, fut).await {
+        Ok(res) => res,
+        Err(_) => Err(ClientError::timeout(format_args!(
+            "{} - not completed in {:?}",
+            op, dur
+        ))),
+    }
+}
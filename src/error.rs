@@ -4,48 +4,170 @@
 // Copyright: 2021, Joylei <leingliu@gmail.com>
 // License: MIT
 
-use crate::client::ab_eip::PathError;
+use crate::{
+    cip::{connection::ForwardOpenError, Status},
+    client::ab_eip::PathError,
+};
 use core::fmt;
 use rseip_core::{Error, String};
 use std::io;
 
+/// classification of [`ClientError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// underline transport failure
+    Io,
+    /// operation not completed in time
+    Timeout,
+    /// message reply with CIP error status
+    Cip,
+    /// encapsulation reply with error status
+    Encapsulation,
+    /// Forward Open rejected by the target
+    ForwardOpen,
+    /// malformed or unexpected data
+    Decode,
+    /// failed to parse tag path
+    Path,
+    /// other errors
+    Custom,
+}
+
+impl ErrorKind {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Io => "io",
+            Self::Timeout => "timeout",
+            Self::Cip => "cip status",
+            Self::Encapsulation => "encapsulation status",
+            Self::ForwardOpen => "forward open",
+            Self::Decode => "decode",
+            Self::Path => "tag path error",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// client error
 #[derive(Debug)]
 pub enum ClientError {
-    Io { kind: &'static str, err: io::Error },
-    Custom { kind: &'static str, msg: String },
+    Io {
+        kind: &'static str,
+        err: io::Error,
+    },
+    Custom {
+        kind: &'static str,
+        msg: String,
+    },
+    Timeout {
+        msg: String,
+    },
+    /// message reply with CIP error status
+    Cip {
+        status: Status,
+    },
+    /// encapsulation reply with error status
+    Encapsulation {
+        code: u16,
+        msg: String,
+    },
+    ForwardOpen {
+        err: ForwardOpenError,
+    },
+    Decode {
+        msg: String,
+    },
+    Path {
+        err: PathError,
+    },
 }
 
 impl ClientError {
-    pub fn kind(&self) -> &'static str {
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
         match self {
-            Self::Io { kind, .. } => kind,
-            Self::Custom { kind, .. } => kind,
+            Self::Io { .. } => ErrorKind::Io,
+            Self::Custom { .. } => ErrorKind::Custom,
+            Self::Timeout { .. } => ErrorKind::Timeout,
+            Self::Cip { .. } => ErrorKind::Cip,
+            Self::Encapsulation { .. } => ErrorKind::Encapsulation,
+            Self::ForwardOpen { .. } => ErrorKind::ForwardOpen,
+            Self::Decode { .. } => ErrorKind::Decode,
+            Self::Path { .. } => ErrorKind::Path,
         }
     }
 
-    pub fn kind_mut(&mut self) -> &mut &'static str {
+    /// operation not completed in time?
+    #[inline]
+    pub fn is_timeout(&self) -> bool {
         match self {
-            Self::Io { kind, .. } => kind,
-            Self::Custom { kind, .. } => kind,
+            Self::Io { err, .. } => err.kind() == io::ErrorKind::TimedOut,
+            Self::Timeout { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// CIP status of message reply or Forward Open reply if any
+    #[inline]
+    pub fn status(&self) -> Option<&Status> {
+        match self {
+            Self::Cip { status } => Some(status),
+            Self::ForwardOpen { err } => Some(&err.status),
+            _ => None,
+        }
+    }
+
+    /// encapsulation status code if any
+    #[inline]
+    pub fn encapsulation_code(&self) -> Option<u16> {
+        match self {
+            Self::Encapsulation { code, .. } => Some(*code),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for ClientError {}
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { err, .. } => Some(err),
+            Self::ForwardOpen { err } => Some(err),
+            Self::Path { err } => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for ClientError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Io { kind, err } => write!(f, "{} - {}", kind, err),
             Self::Custom { kind, msg } => write!(f, "{} - {}", kind, msg),
+            Self::Timeout { msg } => write!(f, "{} - {}", self.kind(), msg),
+            Self::Cip { status } => write!(f, "{} - message reply status {}", self.kind(), status),
+            Self::Encapsulation { msg, .. } => write!(f, "{} - {}", self.kind(), msg),
+            Self::ForwardOpen { err } => write!(f, "{} - {}", self.kind(), err),
+            Self::Decode { msg } => write!(f, "{} - {}", self.kind(), msg),
+            Self::Path { err } => write!(f, "{} - {}", self.kind(), err),
         }
     }
 }
 
 impl Error for ClientError {
-    fn with_kind(mut self, kind: &'static str) -> Self {
-        *self.kind_mut() = kind;
+    /// only applies to [`ClientError::Io`] and [`ClientError::Custom`]
+    fn with_kind(mut self, val: &'static str) -> Self {
+        match self {
+            Self::Io { ref mut kind, .. } | Self::Custom { ref mut kind, .. } => *kind = val,
+            _ => {}
+        }
         self
     }
 
@@ -55,6 +177,46 @@ impl Error for ClientError {
             msg: msg.to_string().into(),
         }
     }
+
+    fn timeout<T: core::fmt::Display>(msg: T) -> Self {
+        Self::Timeout {
+            msg: msg.to_string().into(),
+        }
+    }
+
+    fn cip_status<T: core::fmt::Display>(general: u8, additional: &[u16], _msg: T) -> Self {
+        Self::Cip {
+            status: Status {
+                general,
+                additional: additional.to_vec(),
+            },
+        }
+    }
+
+    fn encapsulation_status<T: core::fmt::Display>(code: u16, msg: T) -> Self {
+        Self::Encapsulation {
+            code,
+            msg: msg.to_string().into(),
+        }
+    }
+
+    fn invalid_type<U: fmt::Display, E: fmt::Display>(unexp: U, exp: E) -> Self {
+        Self::Decode {
+            msg: format!("invalid type: {}, expected {}", unexp, exp).into(),
+        }
+    }
+
+    fn invalid_value<U: fmt::Display, E: fmt::Display>(unexp: U, exp: E) -> Self {
+        Self::Decode {
+            msg: format!("invalid value: {}, expected {}", unexp, exp).into(),
+        }
+    }
+
+    fn invalid_length<E: fmt::Display>(len: usize, exp: E) -> Self {
+        Self::Decode {
+            msg: format!("invalid length: {}, expected {}", len, exp).into(),
+        }
+    }
 }
 
 impl From<io::Error> for ClientError {
@@ -63,8 +225,38 @@ impl From<io::Error> for ClientError {
     }
 }
 
+impl From<ForwardOpenError> for ClientError {
+    fn from(e: ForwardOpenError) -> Self {
+        Self::ForwardOpen { err: e }
+    }
+}
+
 impl From<PathError> for ClientError {
     fn from(e: PathError) -> Self {
-        Self::custom(e).with_kind("tag path error")
+        Self::Path { err: e }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rseip_cip::error::cip_error_status;
+
+    #[test]
+    fn test_error_kind() {
+        let err: ClientError = cip_error_status(Status::new(0x01).with_additional(0x0100));
+        assert_eq!(err.kind(), ErrorKind::Cip);
+        let status = err.status().unwrap();
+        assert_eq!(status.general, 0x01);
+        assert_eq!(status.extended(), Some(0x0100));
+
+        let err = ClientError::encapsulation_status(0x64, "invalid session");
+        assert_eq!(err.kind(), ErrorKind::Encapsulation);
+        assert_eq!(err.encapsulation_code(), Some(0x64));
+
+        assert_eq!(ClientError::invalid_length(1, 2).kind(), ErrorKind::Decode);
+        assert_eq!(ClientError::timeout("x").kind(), ErrorKind::Timeout);
+        assert_eq!(ClientError::from(PathError::Eof).kind(), ErrorKind::Path);
+        assert_eq!(ClientError::custom("x").kind(), ErrorKind::Custom);
     }
 }
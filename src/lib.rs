@@ -231,7 +231,7 @@ pub mod client;
 mod error;
 
 #[doc(inline)]
-pub use error::ClientError;
+pub use error::{ClientError, ErrorKind};
 pub use rseip_cip as cip;
 /// library result
 pub type Result<T> = core::result::Result<T, ClientError>;